@@ -0,0 +1,133 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::convert::TryInto;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signer};
+use obs_controller_core::types::RegistrationResponse;
+use obs_controller_core::verification;
+use ring::aead::{Aad, AES_256_GCM, LessSafeKey, Nonce, UnboundKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use x25519_dalek::EphemeralSecret;
+
+use crate::Error;
+
+/// The key an app signs its requests with, obtained by registering.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Credentials {
+    /// The app UUID, hyphenated
+    pub uuid: String,
+    /// The app's Ed25519 secret key, in Base64
+    pub key: String,
+}
+
+impl Credentials {
+    /// Reads credentials saved with [`Credentials::save`].
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Credentials> {
+        let file = fs::read(path)?;
+        serde_json::from_slice(&file).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Writes the credentials to a file, so the app doesn't have to register again.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        if let Some(parent) = path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_vec(self)?)
+    }
+
+    /// Signs a request body with the legacy scheme, returning the value of the `X-OBSC-Signature` header.
+    /// Such signatures can be replayed, prefer [`Credentials::sign_request`].
+    pub fn sign(&self, body: &str) -> Result<String, Error> {
+        // Empty bodies are signed with a constant message
+        self.sign_message(if body.is_empty() { "obs-controller" } else { body })
+    }
+
+    /// Signs a request with `X-OBSC-Version: 2`, returning the value of the `X-OBSC-Signature` header.
+    /// `path` may include a query, `content_type` is the `Content-Type` of the body if it is sent with one,
+    /// `timestamp` and `nonce` are sent as `X-OBSC-Timestamp` and `X-OBSC-Nonce`.
+    pub fn sign_request(&self, method: &str, path: &str, content_type: Option<&str>, timestamp: u64, nonce: &str,
+                        body: &str) -> Result<String, Error> {
+        let timestamp = timestamp.to_string();
+        let headers = [
+            ("Content-Type", content_type.unwrap_or_default()),
+            ("X-OBSC-App", self.uuid.as_str()),
+            ("X-OBSC-Version", "2"),
+            ("X-OBSC-Timestamp", timestamp.as_str()),
+            ("X-OBSC-Nonce", nonce),
+        ];
+        self.sign_message(&verification::canonical_request(method, path, &headers, body))
+    }
+
+    fn sign_message(&self, msg: &str) -> Result<String, Error> {
+        let secret = base64::decode(&self.key).map_err(|_| Error::Protocol("Invalid Base64 in key"))?;
+        let secret = SecretKey::from_bytes(&secret).map_err(|_| Error::Protocol("Invalid secret key"))?;
+        let public = PublicKey::from(&secret);
+        let pair = Keypair { secret, public };
+        let mut hash = Sha256::new();
+        hash.update(msg.as_bytes());
+        Ok(base64::encode(pair.sign(&hash.finalize()[..]).to_bytes()))
+    }
+}
+
+/// The app side of the registration key exchange.
+pub(crate) struct Handshake {
+    secret: EphemeralSecret,
+    public: x25519_dalek::PublicKey,
+}
+
+impl Handshake {
+    pub fn new() -> Handshake {
+        let secret = EphemeralSecret::new(rand_core::OsRng);
+        let public = x25519_dalek::PublicKey::from(&secret);
+        Handshake { secret, public }
+    }
+
+    /// Our X25519 public key, in Base64.
+    pub fn public_key(&self) -> String {
+        base64::encode(self.public.to_bytes())
+    }
+
+    /// Decrypts the Ed25519 secret key sent by the server, returning it in Base64.
+    /// Servers predating [`verification::REGISTRATION_VERSION`] answer with the legacy protocol, which is still accepted.
+    pub fn finish(self, uuid: Uuid, res: &RegistrationResponse) -> Result<String, Error> {
+        let server_pub: [u8; 32] = base64::decode(&res.shared_public).ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(Error::Protocol("Invalid server public key"))?;
+        let shared = self.secret.diffie_hellman(&x25519_dalek::PublicKey::from(server_pub));
+        let mut secret = base64::decode(&res.key).map_err(|_| Error::Protocol("Invalid Base64 in key"))?;
+        let (aes, nonce) = match (res.version, &res.nonce) {
+            (1, None) => (LessSafeKey::new(UnboundKey::new(&AES_256_GCM, shared.as_bytes()).expect("Couldn't create key")), [0; 12]),
+            (verification::REGISTRATION_VERSION, Some(nonce)) => {
+                let nonce: [u8; 12] = base64::decode(nonce).ok()
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .ok_or(Error::Protocol("Invalid nonce"))?;
+                (verification::registration_key(shared.as_bytes(), uuid, self.public.as_bytes(), &server_pub), nonce)
+            }
+            _ => return Err(Error::Protocol("Unsupported registration protocol"))
+        };
+        let secret = aes.open_in_place(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut secret)
+            .map_err(|_| Error::Protocol("Couldn't decrypt key"))?;
+        Ok(base64::encode(secret))
+    }
+}
@@ -0,0 +1,486 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! A client for the OBS Controller HTTP API.
+//!
+//! ```no_run
+//! # use obs_controller_client::{Client, Credentials};
+//! # use uuid::Uuid;
+//! let mut client = Client::new("http://127.0.0.1:8085");
+//! let credentials = match Credentials::load("obs.json") {
+//!     Ok(credentials) => credentials,
+//!     Err(_) => {
+//!         let credentials = client.register(Uuid::parse_str("98704291-09e9-40f2-8476-064521fadaff")?, "My App")?;
+//!         credentials.save("obs.json")?;
+//!         credentials
+//!     }
+//! };
+//! client.set_credentials(credentials);
+//! client.start_recording(Some("Match %CCYY-%MM-%DD"))?;
+//! let recording = client.stop_recording()?;
+//! println!("Saved to {:?}", recording.path);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fmt;
+use std::io;
+
+use rand_core::{OsRng, RngCore};
+use uuid::Uuid;
+
+pub use crate::credentials::Credentials;
+pub use obs_controller_core::types::{
+    AudioResponse, CollectionsResponse, InfoResponse, Marker, RecordingFormat, RecordingStatus, RegisteredApp, ReplayResponse,
+    SceneItem, SceneResponse, ScenesResponse, StartRequest, StopResponse, StreamingStatus, Volume,
+};
+use obs_controller_core::types::{MarkerRequest, MuteRequest, NameRequest, ReplayRequest, ScreenshotResponse, SourcesResponse, VisibilityRequest};
+use obs_controller_core::types::{AppRegistrationData, AppsResponse, ErrorResponse, RegistrationResponse};
+use obs_controller_core::verification;
+
+mod credentials;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The server answered with an error status, and the error code and message of its body
+    Api { status: u16, code: String, message: String },
+    /// The request couldn't be sent or the response couldn't be read
+    Io(io::Error),
+    /// The server response doesn't follow the protocol
+    Protocol(&'static str),
+    /// An authenticated call was made before registering
+    NoCredentials,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Api { status, code, message } => write!(f, "Server responded with {} ({}): {}", status, code, message),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Protocol(msg) => write!(f, "Protocol error: {}", msg),
+            Error::NoCredentials => f.write_str("The client has no credentials, register first"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<ureq::Error> for Error {
+    fn from(e: ureq::Error) -> Self {
+        match e {
+            ureq::Error::Status(status, res) => {
+                let (code, message) = res.into_string().ok()
+                    .and_then(|body| serde_json::from_str::<ErrorResponse>(&body).ok())
+                    .map(|res| (res.error, res.message))
+                    .unwrap_or_default();
+                Error::Api { status, code, message }
+            }
+            ureq::Error::Transport(e) => Error::Io(io::Error::new(io::ErrorKind::Other, e)),
+        }
+    }
+}
+
+pub struct Client {
+    base_url: String,
+    credentials: Option<Credentials>,
+}
+
+impl Client {
+    /// Creates a client for the server at `base_url`, e.g. `http://127.0.0.1:8085`.
+    pub fn new<S: Into<String>>(base_url: S) -> Client {
+        Client { base_url: base_url.into().trim_end_matches('/').to_string(), credentials: None }
+    }
+
+    pub fn with_credentials<S: Into<String>>(base_url: S, credentials: Credentials) -> Client {
+        let mut client = Client::new(base_url);
+        client.credentials = Some(credentials);
+        client
+    }
+
+    pub fn credentials(&self) -> Option<&Credentials> {
+        self.credentials.as_ref()
+    }
+
+    pub fn set_credentials(&mut self, credentials: Credentials) {
+        self.credentials = Some(credentials);
+    }
+
+    /// Gets the plugin and OBS versions.
+    pub fn info(&self) -> Result<InfoResponse, Error> {
+        let res = ureq::get(&self.url("/")).call()?.into_string()?;
+        parse(&res)
+    }
+
+    /// Registers the app, which blocks until the user has accepted or denied it in OBS.
+    ///
+    /// The returned credentials are also used for the next calls of this client.
+    /// Save them with [`Credentials::save`], as an app can only register once.
+    pub fn register(&mut self, uuid: Uuid, name: &str) -> Result<Credentials, Error> {
+        let handshake = credentials::Handshake::new();
+        let body = serde_json::to_string(&AppRegistrationData {
+            version: verification::REGISTRATION_VERSION,
+            uuid: uuid.to_hyphenated().to_string(),
+            name: name.to_string(),
+            public_key: handshake.public_key(),
+        }).expect("Couldn't serialize registration");
+        let res = ureq::post(&self.url("/register")).send_string(&body)?.into_string()?;
+        let res: RegistrationResponse = parse(&res)?;
+        let credentials = Credentials {
+            uuid: uuid.to_hyphenated().to_string(),
+            key: handshake.finish(uuid, &res)?,
+        };
+        self.credentials = Some(credentials.clone());
+        Ok(credentials)
+    }
+
+    /// Starts recording, with a custom file name format if `name` is set.
+    pub fn start_recording(&self, name: Option<&str>) -> Result<(), Error> {
+        self.start_recording_with(&StartRequest { name: name.map(str::to_string), ..StartRequest::default() })
+    }
+
+    /// Starts recording with a custom file name format, output directory and container format,
+    /// optionally paused. Unset options keep the settings of OBS.
+    pub fn start_recording_with(&self, options: &StartRequest) -> Result<(), Error> {
+        let body = serde_json::to_string(options).expect("Couldn't serialize recording options");
+        self.signed_with("POST", "/recording/start", &body, Some("application/json"))?;
+        Ok(())
+    }
+
+    /// Stops recording, returning the path of the recorded file.
+    pub fn stop_recording(&self) -> Result<StopResponse, Error> {
+        parse(&self.signed("POST", "/recording/stop", "")?)
+    }
+
+    /// Gets the state of the recording output, which might have been started from OBS.
+    pub fn recording_status(&self) -> Result<RecordingStatus, Error> {
+        parse(&self.signed("GET", "/recording/status", "")?)
+    }
+
+    /// Marks the current time of the recording. The markers are returned when stopping.
+    pub fn add_marker(&self, label: &str) -> Result<Marker, Error> {
+        let body = serde_json::to_string(&MarkerRequest { label: label.to_string() }).expect("Couldn't serialize label");
+        parse(&self.signed("POST", "/recording/marker", &body)?)
+    }
+
+    /// Pauses the current recording.
+    pub fn pause_recording(&self) -> Result<(), Error> {
+        self.signed("POST", "/recording/pause", "")?;
+        Ok(())
+    }
+
+    /// Resumes the paused recording.
+    pub fn resume_recording(&self) -> Result<(), Error> {
+        self.signed("POST", "/recording/resume", "")?;
+        Ok(())
+    }
+
+    /// Starts streaming with the stream settings of OBS.
+    pub fn start_streaming(&self) -> Result<(), Error> {
+        self.signed("POST", "/streaming/start", "")?;
+        Ok(())
+    }
+
+    /// Stops streaming.
+    pub fn stop_streaming(&self) -> Result<(), Error> {
+        self.signed("POST", "/streaming/stop", "")?;
+        Ok(())
+    }
+
+    /// Gets the state of the streaming output.
+    pub fn streaming_status(&self) -> Result<StreamingStatus, Error> {
+        parse(&self.signed("GET", "/streaming/status", "")?)
+    }
+
+    /// Starts the replay buffer.
+    pub fn start_replay_buffer(&self) -> Result<(), Error> {
+        self.signed("POST", "/replay/start", "")?;
+        Ok(())
+    }
+
+    /// Stops the replay buffer.
+    pub fn stop_replay_buffer(&self) -> Result<(), Error> {
+        self.signed("POST", "/replay/stop", "")?;
+        Ok(())
+    }
+
+    /// Saves the replay buffer, with a custom file name format if `name` is set,
+    /// returning the path of the saved file once OBS has written it.
+    pub fn save_replay(&self, name: Option<&str>) -> Result<ReplayResponse, Error> {
+        let body = serde_json::to_string(&ReplayRequest { name: name.map(str::to_string) }).expect("Couldn't serialize name");
+        parse(&self.signed("POST", "/replay/save", &body)?)
+    }
+
+    /// Lists the scenes of the current scene collection.
+    pub fn scenes(&self) -> Result<ScenesResponse, Error> {
+        parse(&self.signed("GET", "/scenes", "")?)
+    }
+
+    /// Switches the program scene.
+    pub fn set_scene(&self, name: &str) -> Result<(), Error> {
+        self.signed("PUT", "/scenes/current", &name_body(name))?;
+        Ok(())
+    }
+
+    /// Gets the preview scene, only available in studio mode.
+    pub fn preview_scene(&self) -> Result<SceneResponse, Error> {
+        parse(&self.signed("GET", "/scenes/preview", "")?)
+    }
+
+    /// Switches the preview scene, only available in studio mode.
+    pub fn set_preview_scene(&self, name: &str) -> Result<(), Error> {
+        self.signed("PUT", "/scenes/preview", &name_body(name))?;
+        Ok(())
+    }
+
+    /// Lists the sources of a scene.
+    pub fn sources(&self, scene: &str) -> Result<Vec<SceneItem>, Error> {
+        let res: SourcesResponse = parse(&self.signed("GET", &format!("/scenes/{}/sources", encode(scene)), "")?)?;
+        Ok(res.sources)
+    }
+
+    /// Shows or hides a source in a scene.
+    pub fn set_source_visible(&self, scene: &str, source: &str, visible: bool) -> Result<(), Error> {
+        let body = serde_json::to_string(&VisibilityRequest { visible }).expect("Couldn't serialize visibility");
+        self.signed("PUT", &format!("/scenes/{}/sources/{}/visible", encode(scene), encode(source)), &body)?;
+        Ok(())
+    }
+
+    /// Gets the mute state and volume of an audio source.
+    pub fn source_audio(&self, source: &str) -> Result<AudioResponse, Error> {
+        parse(&self.signed("GET", &format!("/sources/{}/audio", encode(source)), "")?)
+    }
+
+    pub fn set_source_muted(&self, source: &str, muted: bool) -> Result<(), Error> {
+        let body = serde_json::to_string(&MuteRequest { muted }).expect("Couldn't serialize mute");
+        self.signed("PUT", &format!("/sources/{}/mute", encode(source)), &body)?;
+        Ok(())
+    }
+
+    /// Sets the volume of an audio source, with either `db` or `multiplier` set.
+    pub fn set_source_volume(&self, source: &str, volume: Volume) -> Result<(), Error> {
+        let body = serde_json::to_string(&volume).expect("Couldn't serialize volume");
+        self.signed("PUT", &format!("/sources/{}/volume", encode(source)), &body)?;
+        Ok(())
+    }
+
+    /// Renders the program output, or a source, to a PNG image.
+    /// The image has the size of the source, unless `size` is set.
+    pub fn screenshot(&self, source: Option<&str>, size: Option<(u32, u32)>) -> Result<Vec<u8>, Error> {
+        let mut path = String::from("/screenshot?format=base64");
+        if let Some(source) = source {
+            path.push_str(&format!("&source={}", encode(source)));
+        }
+        if let Some((width, height)) = size {
+            path.push_str(&format!("&width={}&height={}", width, height));
+        }
+        let res: ScreenshotResponse = parse(&self.signed("GET", &path, "")?)?;
+        base64::decode(&res.image).map_err(|_| Error::Protocol("Invalid Base64 image"))
+    }
+
+    /// Lists the scene collections.
+    pub fn scene_collections(&self) -> Result<CollectionsResponse, Error> {
+        parse(&self.signed("GET", "/scene-collections", "")?)
+    }
+
+    /// Switches the scene collection.
+    pub fn set_scene_collection(&self, name: &str) -> Result<(), Error> {
+        self.signed("PUT", "/scene-collections/current", &name_body(name))?;
+        Ok(())
+    }
+
+    /// Lists the registered apps, which requires this app to be in `admin_apps` in the plugin config.
+    pub fn apps(&self) -> Result<Vec<RegisteredApp>, Error> {
+        parse::<AppsResponse>(&self.signed("GET", "/apps", "")?).map(|res| res.apps)
+    }
+
+    /// Revokes an app, which requires this app to be in `admin_apps` in the plugin config.
+    /// The revoked app has to register again.
+    pub fn revoke_app(&self, uuid: Uuid) -> Result<(), Error> {
+        self.signed("DELETE", &format!("/apps/{}", uuid.to_hyphenated()), "")?;
+        Ok(())
+    }
+
+    fn signed(&self, method: &str, path: &str, body: &str) -> Result<String, Error> {
+        self.signed_with(method, path, body, None)
+    }
+
+    fn signed_with(&self, method: &str, path: &str, body: &str, content_type: Option<&str>) -> Result<String, Error> {
+        let credentials = self.credentials.as_ref().ok_or(Error::NoCredentials)?;
+        let mut nonce = [0u8; 16];
+        OsRng.fill_bytes(&mut nonce);
+        let nonce = base64::encode(nonce);
+        let timestamp = verification::unix_time();
+        let mut req = ureq::request(method, &self.url(path));
+        if let Some(content_type) = content_type {
+            req = req.set("Content-Type", content_type);
+        }
+        let res = req
+            .set("X-OBSC-App", &credentials.uuid)
+            .set("X-OBSC-Version", &verification::PROTOCOL_VERSION.to_string())
+            .set("X-OBSC-Timestamp", &timestamp.to_string())
+            .set("X-OBSC-Nonce", &nonce)
+            .set("X-OBSC-Signature", &credentials.sign_request(method, path, content_type, timestamp, &nonce, body)?)
+            .send_string(body)?;
+        Ok(res.into_string()?)
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+}
+
+/// The JSON body of the scene and scene collection switching routes.
+fn name_body(name: &str) -> String {
+    serde_json::to_string(&NameRequest { name: name.to_string() }).expect("Couldn't serialize name")
+}
+
+/// Percent-encodes a scene or source name for use in a path.
+fn encode(name: &str) -> String {
+    percent_encoding::utf8_percent_encode(name, percent_encoding::NON_ALPHANUMERIC).to_string()
+}
+
+fn parse<'a, T: serde::Deserialize<'a>>(res: &'a str) -> Result<T, Error> {
+    serde_json::from_str(res).map_err(|_| Error::Protocol("Unexpected response body"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Deref;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use std::thread;
+
+    use obs_controller_core::mock::MockBackend;
+    use obs_controller_core::server::HttpServer;
+    use obs_controller_core::{routes, AppStore, Config, Controller};
+    use rand_core::{OsRng, RngCore};
+    use tiny_http::Server;
+    use uuid::Uuid;
+
+    use super::{Client, Credentials, Error, SceneItem};
+
+    /// A path in the temp directory, whose file is removed on drop.
+    struct TempPath(PathBuf);
+
+    impl TempPath {
+        fn new(ext: &str) -> TempPath {
+            TempPath(std::env::temp_dir().join(format!("obs-controller-client-test-{:x}.{}", OsRng.next_u64(), ext)))
+        }
+    }
+
+    impl Deref for TempPath {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// Runs the server on a loopback port, returning its URL and the path of its app store.
+    fn spawn(backend: Arc<MockBackend>) -> (String, TempPath) {
+        let store = TempPath::new("ock");
+        let controller = Arc::new(Controller::new(backend, AppStore::new(store.to_path_buf()),
+                                                  Box::new(|name: &str| name != "Denied")));
+        let listener = Server::http("127.0.0.1:0").unwrap();
+        let port = listener.server_addr().port();
+        thread::spawn(move || {
+            let mut server = HttpServer::new(Config::default());
+            routes::add_routes(&mut server, controller);
+            server.serve(listener)
+        });
+        (format!("http://127.0.0.1:{}", port), store)
+    }
+
+    #[test]
+    pub fn register_and_record() {
+        let backend = Arc::new(MockBackend::new());
+        let (url, _store) = spawn(Arc::clone(&backend));
+        let mut client = Client::new(url);
+        assert_eq!("mock", client.info().unwrap().obs);
+        client.register(Uuid::parse_str("98704291-09e9-40f2-8476-064521fadaff").unwrap(), "Test").unwrap();
+        client.start_recording(Some("Match")).unwrap();
+        assert!(backend.state().recording);
+        let status = client.recording_status().unwrap();
+        assert!(status.active && status.custom_name);
+        assert_eq!("Round 1", client.add_marker("Round 1").unwrap().label);
+        let res = client.stop_recording().unwrap();
+        assert_eq!(Some("/recordings/Match.mkv".to_string()), res.path);
+        assert_eq!(1, res.markers.len());
+        assert!(!backend.state().recording);
+    }
+
+    #[test]
+    pub fn sources() {
+        let backend = Arc::new(MockBackend::new());
+        {
+            let mut state = backend.state();
+            state.scenes = vec!["Match #1".to_string()];
+            state.scene_items.insert("Match #1".to_string(), vec![SceneItem { name: "Overlay/Score".to_string(), visible: true }]);
+        }
+        let (url, _store) = spawn(Arc::clone(&backend));
+        let mut client = Client::new(url);
+        client.register(Uuid::nil(), "Test").unwrap();
+        client.set_source_visible("Match #1", "Overlay/Score", false).unwrap();
+        assert_eq!(vec![SceneItem { name: "Overlay/Score".to_string(), visible: false }], client.sources("Match #1").unwrap());
+        // Without a frame provider
+        assert!(matches!(client.screenshot(Some("Match #1"), None), Err(Error::Api { status: 501, .. })));
+    }
+
+    #[test]
+    pub fn saved_credentials() {
+        let (url, _store) = spawn(Arc::new(MockBackend::new()));
+        let credentials = Client::new(&url).register(Uuid::nil(), "Test").unwrap();
+        let path = TempPath::new("json");
+        credentials.save(&*path).unwrap();
+        let client = Client::with_credentials(&url, Credentials::load(&*path).unwrap());
+        client.start_recording(None).unwrap();
+    }
+
+    #[test]
+    pub fn errors() {
+        let (url, _store) = spawn(Arc::new(MockBackend::new()));
+        let mut client = Client::new(&url);
+        assert!(matches!(client.stop_recording(), Err(Error::NoCredentials)));
+        match client.register(Uuid::nil(), "Denied") {
+            Err(Error::Api { status, code, message }) => {
+                assert_eq!(401, status);
+                assert_eq!("registration_denied", code);
+                assert_eq!("The registration request was denied by the user", message);
+            }
+            _ => panic!("Registration should be denied"),
+        }
+        // Credentials of an app that isn't registered on this server
+        let client = Client::with_credentials(&url, Credentials { uuid: Uuid::nil().to_string(), key: base64::encode([1u8; 32]) });
+        assert!(matches!(client.start_recording(None), Err(Error::Api { status: 400, code, .. }) if code == "unknown_app"));
+        // Only the apps of admin_apps can manage apps
+        let mut client = Client::new(&url);
+        client.register(Uuid::nil(), "Test").unwrap();
+        assert!(matches!(client.apps(), Err(Error::Api { status: 403, code, .. }) if code == "forbidden"));
+    }
+}
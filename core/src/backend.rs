@@ -0,0 +1,161 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use crate::types::SceneItem;
+
+/// Statistics of an OBS output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OutputStats {
+    pub bytes: u64,
+    pub dropped_frames: u32,
+    pub total_frames: u32,
+}
+
+/// The audio settings of a source.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AudioState {
+    pub muted: bool,
+    /// The volume, as a multiplier
+    pub volume: f32,
+}
+
+/// The subset of the OBS frontend API used by the controller.
+///
+/// The plugin talks to OBS through `ObsFrontend`, tests use `MockBackend` instead.
+pub trait ObsBackend: Send + Sync {
+    /// The version of the running libobs.
+    fn version(&self) -> String;
+
+    /// Starts the recording output.
+    fn recording_start(&self);
+
+    /// Stops the recording output.
+    fn recording_stop(&self);
+
+    /// Whether the recording output is active, paused or not.
+    fn recording_active(&self) -> bool;
+
+    /// Whether the recording output is paused.
+    fn recording_paused(&self) -> bool;
+
+    /// Whether the recording output can be paused.
+    fn recording_can_pause(&self) -> bool;
+
+    /// Pauses or resumes the recording output.
+    fn recording_pause(&self, pause: bool);
+
+    /// The statistics of the recording output, `None` if there's no recording output.
+    fn recording_stats(&self) -> Option<OutputStats>;
+
+    /// Whether the streaming output is active, reconnecting or not.
+    fn streaming_active(&self) -> bool;
+
+    /// Starts the streaming output.
+    fn streaming_start(&self);
+
+    /// Stops the streaming output.
+    fn streaming_stop(&self);
+
+    /// The statistics of the streaming output, `None` if there's no streaming output.
+    fn streaming_stats(&self) -> Option<OutputStats>;
+
+    /// Whether the streaming output lost its connection and is reconnecting.
+    fn streaming_reconnecting(&self) -> bool;
+
+    /// The congestion of the streaming output, from 0 to 1.
+    fn streaming_congestion(&self) -> f32;
+
+    /// Whether the replay buffer output is active.
+    fn replay_buffer_active(&self) -> bool;
+
+    /// Starts the replay buffer output.
+    fn replay_buffer_start(&self);
+
+    /// Stops the replay buffer output.
+    fn replay_buffer_stop(&self);
+
+    /// Saves the replay buffer. OBS reports the saved file through a frontend event.
+    fn replay_buffer_save(&self);
+
+    /// The path of the last saved replay.
+    fn replay_buffer_last_path(&self) -> Option<String>;
+
+    /// Reads a string value from the replay buffer output settings.
+    fn replay_buffer_setting(&self, name: &str) -> Option<String>;
+
+    /// Sets a string value in the replay buffer output settings, used by the next save.
+    fn replay_buffer_set_setting(&self, name: &str, value: &str);
+
+    /// Reads a string value from the current profile config.
+    fn profile_config_get(&self, section: &str, name: &str) -> Option<String>;
+
+    /// Sets a string value in the current profile config in memory only, without saving it.
+    fn profile_config_override(&self, section: &str, name: &str, value: &str);
+
+    /// Removes a value from the current profile config in memory only, without saving it.
+    fn profile_config_remove(&self, section: &str, name: &str);
+
+    /// Reads a string value from the recording output settings.
+    /// Returns `None` if there's no recording output or the value is missing.
+    fn recording_output_setting(&self, name: &str) -> Option<String>;
+
+    /// The name of the current program scene.
+    fn current_scene(&self) -> Option<String>;
+
+    /// The names of the scenes in the current scene collection.
+    fn scene_names(&self) -> Vec<String>;
+
+    /// Switches the program scene to an existing scene.
+    fn set_current_scene(&self, name: &str);
+
+    /// Whether studio mode, with separate preview and program scenes, is enabled.
+    fn studio_mode_active(&self) -> bool;
+
+    /// The name of the current preview scene, in studio mode.
+    fn preview_scene(&self) -> Option<String>;
+
+    /// Switches the preview scene to an existing scene, in studio mode.
+    fn set_preview_scene(&self, name: &str);
+
+    /// The sources of a scene, `None` if there's no scene with this name.
+    fn scene_items(&self, scene: &str) -> Option<Vec<SceneItem>>;
+
+    /// Shows or hides an existing source in a scene.
+    fn set_scene_item_visible(&self, scene: &str, source: &str, visible: bool);
+
+    /// Whether there's a source with this name.
+    fn source_exists(&self, name: &str) -> bool;
+
+    /// The audio settings of a source, `None` if it doesn't exist or has no audio.
+    fn source_audio(&self, name: &str) -> Option<AudioState>;
+
+    /// Mutes or unmutes an existing audio source.
+    fn set_source_muted(&self, name: &str, muted: bool);
+
+    /// Sets the volume of an existing audio source, as a multiplier.
+    fn set_source_volume(&self, name: &str, volume: f32);
+
+    /// The names of the scene collections.
+    fn scene_collections(&self) -> Vec<String>;
+
+    /// The name of the current scene collection.
+    fn current_scene_collection(&self) -> Option<String>;
+
+    /// Switches to an existing scene collection.
+    fn set_scene_collection(&self, name: &str);
+}
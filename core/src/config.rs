@@ -0,0 +1,108 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// User settings, persisted as JSON.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// The interface the server listens on
+    pub bind_address: IpAddr,
+    pub port: u16,
+    /// How many ports to try, starting from `port`, when it's already in use
+    pub port_attempts: u16,
+    /// Other addresses to listen on, e.g. `[::1]:8085`
+    pub extra_addresses: Vec<SocketAddr>,
+    /// How many requests are handled at the same time
+    pub workers: usize,
+    /// Whether recording markers are also written as FFmpeg chapters, next to the JSON file
+    pub ffmpeg_chapters: bool,
+    /// Whether requests signed without `X-OBSC-Version: 2`, which can be replayed, are still accepted (deprecated)
+    pub legacy_signatures: bool,
+    /// The UUIDs of the apps allowed to list and revoke the registered apps
+    pub admin_apps: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bind_address: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 8085,
+            port_attempts: 10,
+            extra_addresses: Vec::new(),
+            workers: 4,
+            ffmpeg_chapters: false,
+            legacy_signatures: false,
+            admin_apps: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config file, creating it with the default settings if it doesn't exist.
+    pub fn load_or_create<P: AsRef<Path>>(path: P) -> io::Result<Config> {
+        match fs::read(path.as_ref()) {
+            Ok(file) => serde_json::from_slice(&file).map_err(|e| io::Error::new(ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                let config = Config::default();
+                config.save(path)?;
+                Ok(config)
+            }
+            Err(e) => Err(e)
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        if let Some(parent) = path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use rand_core::{OsRng, RngCore};
+
+    use super::Config;
+
+    #[test]
+    pub fn create_default() {
+        let path = std::env::temp_dir().join(format!("obs-controller-config-{:x}.json", OsRng.next_u64()));
+        assert_eq!(Config::default(), Config::load_or_create(&path).unwrap());
+        assert!(path.exists());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    pub fn parse() {
+        let config: Config = serde_json::from_str(r#"{"port": 9000, "extra_addresses": ["[::1]:9000", "192.168.1.2:9000"]}"#).unwrap();
+        assert_eq!(9000, config.port);
+        assert_eq!(Config::default().bind_address, config.bind_address);
+        assert_eq!("[::1]:9000".parse::<SocketAddr>().unwrap(), config.extra_addresses[0]);
+        assert!(serde_json::from_str::<Config>(r#"{"bind_address": "localhost"}"#).is_err());
+    }
+}
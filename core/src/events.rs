@@ -0,0 +1,170 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+/// The OBS frontend events forwarded to clients.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    RecordingStarting,
+    RecordingStarted,
+    RecordingStopping,
+    RecordingStopped,
+    RecordingPaused,
+    RecordingUnpaused,
+    StreamingStarting,
+    StreamingStarted,
+    StreamingStopping,
+    StreamingStopped,
+    ReplayBufferStarting,
+    ReplayBufferStarted,
+    ReplayBufferStopping,
+    ReplayBufferStopped,
+    ReplayBufferSaved,
+    SceneChanged,
+}
+
+impl EventKind {
+    pub fn is_recording(self) -> bool {
+        matches!(self, EventKind::RecordingStarting | EventKind::RecordingStarted | EventKind::RecordingStopping
+            | EventKind::RecordingStopped | EventKind::RecordingPaused | EventKind::RecordingUnpaused)
+    }
+}
+
+/// How many past events are kept for clients that resume a stream.
+const HISTORY_SIZE: usize = 256;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Event {
+    /// Increasing number, assigned by the [`EventBus`]
+    pub id: u64,
+    #[serde(rename = "type")]
+    pub kind: EventKind,
+    /// Milliseconds since the Unix epoch
+    pub timestamp: u64,
+    /// The output file, for recording events and saved replays
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// The new scene, for scene changes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scene: Option<String>,
+}
+
+impl Event {
+    pub fn new(kind: EventKind) -> Event {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or_default();
+        Event { id: 0, kind, timestamp, path: None, scene: None }
+    }
+}
+
+/// Sends events to every subscribed client, and remembers the latest ones.
+#[derive(Default)]
+pub struct EventBus {
+    state: Mutex<BusState>,
+}
+
+#[derive(Default)]
+struct BusState {
+    subscribers: Vec<Sender<Arc<Event>>>,
+    history: VecDeque<Arc<Event>>,
+    last_id: u64,
+}
+
+impl EventBus {
+    /// Returns a receiver for the events published from now on.
+    pub fn subscribe(&self) -> Receiver<Arc<Event>> {
+        self.subscribe_after(None).1
+    }
+
+    /// Like [`EventBus::subscribe`], but also returns the remembered events published after `last_id`.
+    pub fn subscribe_after(&self, last_id: Option<u64>) -> (Vec<Arc<Event>>, Receiver<Arc<Event>>) {
+        let (tx, rx) = mpsc::channel();
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let missed = match last_id {
+            Some(last_id) => state.history.iter().filter(|e| e.id > last_id).cloned().collect(),
+            None => Vec::new()
+        };
+        state.subscribers.push(tx);
+        (missed, rx)
+    }
+
+    pub fn publish(&self, mut event: Event) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.last_id += 1;
+        event.id = state.last_id;
+        let event = Arc::new(event);
+        if state.history.len() == HISTORY_SIZE {
+            state.history.pop_front();
+        }
+        state.history.push_back(Arc::clone(&event));
+        // Receivers are dropped when their client disconnects
+        state.subscribers.retain(|tx| tx.send(Arc::clone(&event)).is_ok());
+    }
+
+    /// Disconnects every subscriber.
+    pub fn close(&self) {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).subscribers.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Event, EventBus, EventKind};
+
+    #[test]
+    pub fn publish() {
+        let bus = EventBus::default();
+        let first = bus.subscribe();
+        let second = bus.subscribe();
+        drop(second);
+        bus.publish(Event::new(EventKind::RecordingStarted));
+        assert_eq!(EventKind::RecordingStarted, first.recv().unwrap().kind);
+        assert_eq!(1, bus.state.lock().unwrap().subscribers.len());
+        bus.close();
+        assert!(first.recv().is_err());
+    }
+
+    #[test]
+    pub fn resume() {
+        let bus = EventBus::default();
+        for _ in 0..(super::HISTORY_SIZE + 10) {
+            bus.publish(Event::new(EventKind::SceneChanged));
+        }
+        let (missed, _) = bus.subscribe_after(Some(super::HISTORY_SIZE as u64));
+        assert_eq!((super::HISTORY_SIZE as u64 + 1..=super::HISTORY_SIZE as u64 + 10).collect::<Vec<_>>(),
+                   missed.iter().map(|e| e.id).collect::<Vec<_>>());
+        // Older events were dropped from the history
+        let (missed, _) = bus.subscribe_after(Some(0));
+        assert_eq!(super::HISTORY_SIZE, missed.len());
+        assert_eq!(11, missed[0].id);
+        assert!(bus.subscribe_after(None).0.is_empty());
+    }
+
+    #[test]
+    pub fn json() {
+        let mut event = Event::new(EventKind::ReplayBufferStarted);
+        event.timestamp = 1600000000000;
+        assert_eq!(r#"{"id":0,"type":"replay_buffer_started","timestamp":1600000000000}"#, serde_json::to_string(&event).unwrap());
+    }
+}
@@ -0,0 +1,108 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! The HTTP API of OBS Controller.
+//!
+//! This crate doesn't depend on OBS or Qt: the OBS plugin provides an [`ObsBackend`]
+//! and an [`Approver`], test harnesses can use [`MockBackend`](mock::MockBackend).
+
+use std::sync::Arc;
+
+use crate::events::{Event, EventBus, EventKind};
+
+pub use crate::backend::ObsBackend;
+pub use crate::config::Config;
+pub use crate::verification::{Approver, AppStore};
+
+pub mod backend;
+pub mod config;
+pub mod events;
+pub mod markers;
+pub mod mock;
+pub mod recording;
+pub mod replay;
+pub mod router;
+pub mod routes;
+pub mod scenes;
+pub mod screenshot;
+pub mod server;
+pub mod sources;
+pub mod sse;
+pub mod streaming;
+pub mod types;
+pub mod verification;
+pub mod websocket;
+
+/// Everything the routes need to serve requests.
+pub struct Controller {
+    pub recorder: recording::Recorder,
+    pub replay: replay::ReplayBuffer,
+    pub streamer: streaming::Streamer,
+    pub scenes: scenes::Scenes,
+    pub sources: sources::Sources,
+    pub apps: AppStore,
+    pub approver: Box<dyn Approver>,
+    pub events: EventBus,
+    pub frames: Arc<dyn screenshot::FrameProvider>,
+}
+
+impl Controller {
+    pub fn new(backend: Arc<dyn ObsBackend>, apps: AppStore, approver: Box<dyn Approver>) -> Controller {
+        Controller {
+            recorder: recording::Recorder::new(Arc::clone(&backend)),
+            replay: replay::ReplayBuffer::new(Arc::clone(&backend)),
+            streamer: streaming::Streamer::new(Arc::clone(&backend)),
+            scenes: scenes::Scenes::new(Arc::clone(&backend)),
+            sources: sources::Sources::new(backend),
+            apps,
+            approver,
+            events: EventBus::default(),
+            frames: Arc::new(screenshot::NoFrames),
+        }
+    }
+
+    /// Sets the frame provider used for screenshots, which are unsupported by default.
+    pub fn with_frames(mut self, frames: Arc<dyn screenshot::FrameProvider>) -> Controller {
+        self.frames = frames;
+        self
+    }
+
+    /// Handles an OBS frontend event, forwarding it to the event clients.
+    pub fn handle_event(&self, kind: EventKind) {
+        let mut event = Event::new(kind);
+        if kind.is_recording() {
+            event.path = self.recorder.path();
+        }
+        if kind == EventKind::ReplayBufferSaved {
+            event.path = self.replay.path();
+        }
+        if kind == EventKind::SceneChanged {
+            event.scene = self.recorder.backend().current_scene();
+        }
+        match kind {
+            EventKind::RecordingStarted => self.recorder.on_started(),
+            EventKind::RecordingPaused => self.recorder.on_paused(true),
+            EventKind::RecordingUnpaused => self.recorder.on_paused(false),
+            EventKind::RecordingStopped => self.recorder.on_stopped(),
+            EventKind::StreamingStarted => self.streamer.on_started(),
+            EventKind::StreamingStopped => self.streamer.on_stopped(),
+            _ => {}
+        }
+        self.events.publish(event);
+    }
+}
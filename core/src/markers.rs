@@ -0,0 +1,85 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Sidecar files listing the markers of a recording.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::types::Marker;
+
+/// Writes the markers next to the recording: as JSON in `<name>.markers.json`,
+/// and, if `ffmpeg_chapters` is set, as FFmpeg chapters in `<name>.ffmetadata`.
+/// # Arguments
+/// * `recording` - The path of the recorded file.
+/// * `duration` - The length of the recording, where the last chapter ends.
+pub fn write_sidecars(recording: &Path, markers: &[Marker], duration: Duration, ffmpeg_chapters: bool) -> io::Result<()> {
+    fs::write(recording.with_extension("markers.json"), serde_json::to_vec_pretty(markers)?)?;
+    if ffmpeg_chapters {
+        fs::write(recording.with_extension("ffmetadata"), ffmetadata(markers, duration))?;
+    }
+    Ok(())
+}
+
+/// Formats the markers as FFmpeg metadata, with a chapter from each marker to the next one.
+/// It can be added to the recording with `ffmpeg -i video.mkv -i video.ffmetadata -map_metadata 1 -codec copy out.mkv`.
+pub fn ffmetadata(markers: &[Marker], duration: Duration) -> String {
+    let mut res = String::from(";FFMETADATA1\n");
+    for (i, marker) in markers.iter().enumerate() {
+        let end = markers.get(i + 1).map_or(duration.as_millis() as u64, |next| next.offset);
+        write!(res, "\n[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+               marker.offset, end.max(marker.offset), escape(&marker.label)).unwrap();
+    }
+    res
+}
+
+/// Escapes the characters with a meaning in FFmpeg metadata.
+fn escape(value: &str) -> String {
+    let mut res = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::types::Marker;
+
+    use super::ffmetadata;
+
+    #[test]
+    pub fn chapters() {
+        let markers = [
+            Marker { label: "Round 1".to_string(), offset: 1500 },
+            Marker { label: "Kill; 3=2".to_string(), offset: 64000 },
+        ];
+        assert_eq!(";FFMETADATA1\n\
+                    \n[CHAPTER]\nTIMEBASE=1/1000\nSTART=1500\nEND=64000\ntitle=Round 1\n\
+                    \n[CHAPTER]\nTIMEBASE=1/1000\nSTART=64000\nEND=90000\ntitle=Kill\\; 3\\=2\n",
+                   ffmetadata(&markers, Duration::from_secs(90)));
+    }
+}
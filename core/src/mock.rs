@@ -0,0 +1,338 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::backend::{AudioState, ObsBackend, OutputStats};
+use crate::screenshot::{CaptureError, Frame, FrameProvider};
+use crate::types::SceneItem;
+
+/// In-memory [`ObsBackend`], used to run the API without OBS.
+#[derive(Default)]
+pub struct MockBackend {
+    state: Mutex<MockState>
+}
+
+pub struct MockState {
+    pub recording: bool,
+    /// Makes the recording output fail to start
+    pub fail_start: bool,
+    pub paused: bool,
+    pub can_pause: bool,
+    /// Statistics of the recording output
+    pub stats: OutputStats,
+    /// Profile config values, keyed by (section, name)
+    pub config: HashMap<(String, String), String>,
+    pub output_settings: HashMap<String, String>,
+    /// The program scene
+    pub scene: Option<String>,
+    pub scenes: Vec<String>,
+    pub studio_mode: bool,
+    pub preview: Option<String>,
+    /// The sources of each scene
+    pub scene_items: HashMap<String, Vec<SceneItem>>,
+    /// Sources without audio have no entry
+    pub audio: HashMap<String, AudioState>,
+    /// The size of frames rendered by the [`FrameProvider`] implementation
+    pub base_size: (u32, u32),
+    pub collections: Vec<String>,
+    pub collection: Option<String>,
+    pub streaming: bool,
+    pub reconnecting: bool,
+    pub congestion: f32,
+    /// Statistics of the streaming output
+    pub stream_stats: OutputStats,
+    pub replay_active: bool,
+    pub replay_settings: HashMap<String, String>,
+    pub last_replay: Option<String>,
+    /// Called after the replay buffer is saved, in place of the OBS frontend event
+    pub on_replay_saved: Option<Arc<dyn Fn() + Send + Sync>>
+}
+
+impl Default for MockState {
+    fn default() -> MockState {
+        MockState {
+            recording: false,
+            fail_start: false,
+            paused: false,
+            can_pause: true,
+            stats: OutputStats::default(),
+            config: HashMap::new(),
+            output_settings: HashMap::new(),
+            scene: None,
+            scenes: Vec::new(),
+            studio_mode: false,
+            preview: None,
+            scene_items: HashMap::new(),
+            audio: HashMap::new(),
+            base_size: (32, 18),
+            collections: Vec::new(),
+            collection: None,
+            streaming: false,
+            reconnecting: false,
+            congestion: 0.0,
+            stream_stats: OutputStats::default(),
+            replay_active: false,
+            replay_settings: HashMap::new(),
+            last_replay: None,
+            on_replay_saved: None
+        }
+    }
+}
+
+impl MockBackend {
+    pub fn new() -> MockBackend {
+        let backend = MockBackend::default();
+        backend.config_insert("Output", "FilenameFormatting", "%CCYY-%MM-%DD %hh-%mm-%ss");
+        backend.config_insert("SimpleOutput", "FilePath", "/recordings");
+        backend.config_insert("SimpleOutput", "RecFormat", "mkv");
+        backend.state().replay_settings.insert("format".to_string(), "Replay %CCYY-%MM-%DD %hh-%mm-%ss".to_string());
+        backend
+    }
+
+    /// Gives access to the fake OBS state, to set it up or inspect it.
+    pub fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Sets a profile config value, like the OBS settings do.
+    pub fn config_insert(&self, section: &str, name: &str, value: &str) {
+        self.state().config.insert((section.to_string(), name.to_string()), value.to_string());
+    }
+}
+
+impl ObsBackend for MockBackend {
+    fn version(&self) -> String {
+        String::from("mock")
+    }
+
+    fn recording_start(&self) {
+        let mut state = self.state();
+        if state.fail_start {
+            return;
+        }
+        // Mimic OBS, which builds the output path from the profile config
+        let config = |section: &str, name: &str| state.config.get(&(section.to_string(), name.to_string()))
+            .cloned().unwrap_or_default();
+        let (directory, format) = if config("Output", "Mode") != "Advanced" {
+            (config("SimpleOutput", "FilePath"), config("SimpleOutput", "RecFormat"))
+        } else if config("AdvOut", "RecType") == "FFmpeg" {
+            (config("AdvOut", "FFFilePath"), config("AdvOut", "FFExtension"))
+        } else {
+            (config("AdvOut", "RecFilePath"), config("AdvOut", "RecFormat"))
+        };
+        let path = format!("{}/{}.{}", directory, config("Output", "FilenameFormatting"), format);
+        state.output_settings.insert("path".to_string(), path);
+        state.recording = true;
+    }
+
+    fn recording_stop(&self) {
+        let mut state = self.state();
+        state.recording = false;
+        state.paused = false;
+    }
+
+    fn recording_active(&self) -> bool {
+        self.state().recording
+    }
+
+    fn recording_paused(&self) -> bool {
+        self.state().paused
+    }
+
+    fn recording_can_pause(&self) -> bool {
+        self.state().can_pause
+    }
+
+    fn recording_pause(&self, pause: bool) {
+        let mut state = self.state();
+        state.paused = state.recording && pause;
+    }
+
+    fn recording_stats(&self) -> Option<OutputStats> {
+        Some(self.state().stats)
+    }
+
+    fn streaming_active(&self) -> bool {
+        self.state().streaming
+    }
+
+    fn streaming_start(&self) {
+        self.state().streaming = true;
+    }
+
+    fn streaming_stop(&self) {
+        let mut state = self.state();
+        state.streaming = false;
+        state.reconnecting = false;
+    }
+
+    fn streaming_stats(&self) -> Option<OutputStats> {
+        Some(self.state().stream_stats)
+    }
+
+    fn streaming_reconnecting(&self) -> bool {
+        self.state().reconnecting
+    }
+
+    fn streaming_congestion(&self) -> f32 {
+        self.state().congestion
+    }
+
+    fn replay_buffer_active(&self) -> bool {
+        self.state().replay_active
+    }
+
+    fn replay_buffer_start(&self) {
+        self.state().replay_active = true;
+    }
+
+    fn replay_buffer_stop(&self) {
+        self.state().replay_active = false;
+    }
+
+    fn replay_buffer_save(&self) {
+        let callback = {
+            let mut state = self.state();
+            if !state.replay_active {
+                return;
+            }
+            let name = state.replay_settings.get("format").cloned().unwrap_or_default();
+            state.last_replay = Some(format!("/replays/{}.mkv", name));
+            state.on_replay_saved.clone()
+        };
+        if let Some(callback) = callback {
+            callback();
+        }
+    }
+
+    fn replay_buffer_last_path(&self) -> Option<String> {
+        self.state().last_replay.clone()
+    }
+
+    fn replay_buffer_setting(&self, name: &str) -> Option<String> {
+        self.state().replay_settings.get(name).cloned()
+    }
+
+    fn replay_buffer_set_setting(&self, name: &str, value: &str) {
+        self.state().replay_settings.insert(name.to_string(), value.to_string());
+    }
+
+    fn profile_config_get(&self, section: &str, name: &str) -> Option<String> {
+        self.state().config.get(&(section.to_string(), name.to_string())).cloned()
+    }
+
+    fn profile_config_override(&self, section: &str, name: &str, value: &str) {
+        self.config_insert(section, name, value);
+    }
+
+    fn profile_config_remove(&self, section: &str, name: &str) {
+        self.state().config.remove(&(section.to_string(), name.to_string()));
+    }
+
+    fn recording_output_setting(&self, name: &str) -> Option<String> {
+        self.state().output_settings.get(name).cloned()
+    }
+
+    fn current_scene(&self) -> Option<String> {
+        self.state().scene.clone()
+    }
+
+    fn scene_names(&self) -> Vec<String> {
+        self.state().scenes.clone()
+    }
+
+    fn set_current_scene(&self, name: &str) {
+        self.state().scene = Some(name.to_string());
+    }
+
+    fn studio_mode_active(&self) -> bool {
+        self.state().studio_mode
+    }
+
+    fn preview_scene(&self) -> Option<String> {
+        self.state().preview.clone()
+    }
+
+    fn set_preview_scene(&self, name: &str) {
+        self.state().preview = Some(name.to_string());
+    }
+
+    fn scene_items(&self, scene: &str) -> Option<Vec<SceneItem>> {
+        let state = self.state();
+        if !state.scenes.iter().any(|name| name == scene) {
+            return None;
+        }
+        Some(state.scene_items.get(scene).cloned().unwrap_or_default())
+    }
+
+    fn set_scene_item_visible(&self, scene: &str, source: &str, visible: bool) {
+        let mut state = self.state();
+        let items = state.scene_items.entry(scene.to_string()).or_default();
+        items.iter_mut().filter(|item| item.name == source).for_each(|item| item.visible = visible);
+    }
+
+    fn source_exists(&self, name: &str) -> bool {
+        let state = self.state();
+        state.audio.contains_key(name) || state.scenes.iter().any(|scene| scene == name)
+            || state.scene_items.values().flatten().any(|item| item.name == name)
+    }
+
+    fn source_audio(&self, name: &str) -> Option<AudioState> {
+        self.state().audio.get(name).copied()
+    }
+
+    fn set_source_muted(&self, name: &str, muted: bool) {
+        if let Some(audio) = self.state().audio.get_mut(name) {
+            audio.muted = muted;
+        }
+    }
+
+    fn set_source_volume(&self, name: &str, volume: f32) {
+        if let Some(audio) = self.state().audio.get_mut(name) {
+            audio.volume = volume;
+        }
+    }
+
+    fn scene_collections(&self) -> Vec<String> {
+        self.state().collections.clone()
+    }
+
+    fn current_scene_collection(&self) -> Option<String> {
+        self.state().collection.clone()
+    }
+
+    fn set_scene_collection(&self, name: &str) {
+        self.state().collection = Some(name.to_string());
+    }
+}
+
+/// Renders frames filled with a single color, different for the program output and sources.
+impl FrameProvider for MockBackend {
+    fn capture(&self, source: Option<&str>, size: Option<(u32, u32)>) -> Result<Frame, CaptureError> {
+        let color = match source {
+            Some(name) if !self.source_exists(name) => return Err(CaptureError::UnknownSource),
+            Some(_) => [0, 0, 255, 255],
+            None => [0, 255, 0, 255]
+        };
+        let (width, height) = size.unwrap_or(self.state().base_size);
+        let data = color.iter().copied().cycle().take(width as usize * height as usize * 4).collect();
+        Ok(Frame { width, height, data })
+    }
+}
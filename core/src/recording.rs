@@ -0,0 +1,622 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, TryLockError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::backend::ObsBackend;
+use crate::markers;
+use crate::types::{Marker, RecordingStatus, StartRequest, StopResponse};
+
+const OUTPUT: &str = "Output";
+const FILENAME_FORMATTING: &str = "FilenameFormatting";
+const SIMPLE_OUTPUT: &str = "SimpleOutput";
+const ADVANCED_OUTPUT: &str = "AdvOut";
+/// How long OBS has to start the recording output.
+const START_TIMEOUT: Duration = Duration::from_secs(5);
+const START_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A profile setting changed for a single recording, the previous value is restored once it has started.
+struct Override {
+    section: &'static str,
+    name: &'static str,
+    /// `None` if the setting was missing, in which case it is removed again
+    previous: Option<String>
+}
+
+pub struct RecordingState {
+    overrides: Vec<Override>,
+    /// Whether the recording was started with a custom file name formatting
+    custom_name: bool,
+    started: Instant,
+    paused_since: Option<Instant>,
+    paused_for: Duration,
+    markers: Vec<Marker>
+}
+
+/// Why a recording couldn't be started, paused or resumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingError {
+    NotRecording,
+    AlreadyRecording,
+    /// The output directory doesn't exist.
+    InvalidDirectory,
+    /// The file name formatting contains a NUL character, which OBS can't store.
+    InvalidName,
+    /// The custom FFmpeg output of the advanced mode sets its own container format.
+    FormatUnsupported,
+    /// OBS didn't start the recording output in time, e.g. because the output folder doesn't exist.
+    StartFailed,
+    /// The recording output, e.g. one using a custom FFmpeg output, can't be paused.
+    PauseUnsupported,
+    AlreadyPaused,
+    NotPaused
+}
+
+impl RecordingError {
+    /// The HTTP status reported for this error.
+    pub fn status(self) -> u16 {
+        match self {
+            RecordingError::InvalidDirectory | RecordingError::InvalidName => 400,
+            RecordingError::StartFailed => 500,
+            _ => 409
+        }
+    }
+
+    /// The error code reported for this error.
+    pub fn code(self) -> &'static str {
+        match self {
+            RecordingError::NotRecording => "not_recording",
+            RecordingError::AlreadyRecording => "already_recording",
+            RecordingError::InvalidDirectory => "invalid_directory",
+            RecordingError::InvalidName => "invalid_name",
+            RecordingError::FormatUnsupported => "format_unsupported",
+            RecordingError::StartFailed => "start_failed",
+            RecordingError::PauseUnsupported => "pause_unsupported",
+            RecordingError::AlreadyPaused => "already_paused",
+            RecordingError::NotPaused => "not_paused"
+        }
+    }
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RecordingError::NotRecording => "Not recording",
+            RecordingError::AlreadyRecording => "Already recording",
+            RecordingError::InvalidDirectory => "The output directory does not exist",
+            RecordingError::InvalidName => "The file name formatting must not contain NUL characters",
+            RecordingError::FormatUnsupported => "The recording output does not support setting the container format",
+            RecordingError::StartFailed => "The recording failed to start",
+            RecordingError::PauseUnsupported => "The recording output does not support pausing",
+            RecordingError::AlreadyPaused => "The recording is already paused",
+            RecordingError::NotPaused => "The recording is not paused"
+        })
+    }
+}
+
+impl RecordingState {
+    /// Starts recording with the file name formatting, output directory and container format of `options`.
+    /// Nothing is changed if one of the options can't be applied.
+    ///
+    /// OBS generates the output path from the profile config when the recording starts, so the options
+    /// are set there, in memory only. OBS saves the profile config itself, e.g. when applying its settings,
+    /// so they must be reverted as soon as the recording output is active.
+    /// # Arguments
+    /// * `backend` - The OBS instance to record on.
+    /// * `options` - The settings of this recording. Refer to the OBS documentation for the placeholders of the name.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use std::{thread, time::Duration};
+    /// # use obs_controller_core::backend::ObsBackend;
+    /// # use obs_controller_core::mock::MockBackend;
+    /// # use obs_controller_core::recording::RecordingState;
+    /// # use obs_controller_core::types::StartRequest;
+    /// let backend = MockBackend::new();
+    /// let options = StartRequest { name: Some("Test Recording Name".to_string()), ..StartRequest::default() };
+    /// let mut recording = RecordingState::start_with(&backend, &options).unwrap();
+    /// while !backend.recording_active() {
+    ///     thread::sleep(Duration::from_millis(50));
+    /// }
+    /// recording.revert_overrides(&backend);
+    /// ```
+    pub fn start_with(backend: &dyn ObsBackend, options: &StartRequest) -> Result<RecordingState, RecordingError> {
+        // The simple and advanced output modes keep their recording settings in different places
+        let (section, path, format) = if backend.profile_config_get(OUTPUT, "Mode").as_deref() != Some("Advanced") {
+            (SIMPLE_OUTPUT, "FilePath", Some("RecFormat"))
+        } else if backend.profile_config_get(ADVANCED_OUTPUT, "RecType").as_deref() == Some("FFmpeg") {
+            (ADVANCED_OUTPUT, "FFFilePath", None)
+        } else {
+            (ADVANCED_OUTPUT, "RecFilePath", Some("RecFormat"))
+        };
+        if options.name.as_deref().map_or(false, |name| name.contains('\0')) {
+            return Err(RecordingError::InvalidName);
+        }
+        if let Some(directory) = &options.directory {
+            if directory.contains('\0') || !Path::new(directory).is_dir() {
+                return Err(RecordingError::InvalidDirectory);
+            }
+        }
+        if options.format.is_some() && format.is_none() {
+            return Err(RecordingError::FormatUnsupported);
+        }
+        if options.pause_on_start && !backend.recording_can_pause() {
+            return Err(RecordingError::PauseUnsupported);
+        }
+
+        let mut recording = RecordingState::new();
+        if let Some(name) = options.name.as_deref().filter(|name| !name.is_empty()) {
+            recording.set_override(backend, OUTPUT, FILENAME_FORMATTING, name);
+            recording.custom_name = true;
+        }
+        if let Some(directory) = &options.directory {
+            recording.set_override(backend, section, path, directory);
+        }
+        if let (Some(value), Some(format)) = (options.format, format) {
+            recording.set_override(backend, section, format, value.extension());
+        }
+        backend.recording_start();
+        Ok(recording)
+    }
+
+    fn new() -> RecordingState {
+        RecordingState { overrides: Vec::new(), custom_name: false, started: Instant::now(), paused_since: None, paused_for: Duration::ZERO, markers: Vec::new() }
+    }
+
+    /// Sets a profile setting until the recording has started, storing the previous value.
+    fn set_override(&mut self, backend: &dyn ObsBackend, section: &'static str, name: &'static str, value: &str) {
+        let previous = backend.profile_config_get(section, name);
+        backend.profile_config_override(section, name, value);
+        self.overrides.push(Override { section, name, previous });
+    }
+
+    /// Reverts the file name formatting, output directory and container format to the saved ones.
+    pub fn revert_overrides(&mut self, backend: &dyn ObsBackend) {
+        // In reverse order, in case a setting was overridden twice
+        for setting in self.overrides.drain(..).rev() {
+            match setting.previous {
+                Some(previous) => backend.profile_config_override(setting.section, setting.name, &previous),
+                None => backend.profile_config_remove(setting.section, setting.name)
+            }
+        }
+    }
+
+    /// Stops the current recording.
+    pub fn stop(backend: &dyn ObsBackend) -> StopResponse {
+        let path = RecordingState::recording_path(backend);
+        backend.recording_stop();
+        StopResponse { path, markers: Vec::new() }
+    }
+
+    /// Pauses the current recording.
+    pub fn pause(&mut self, backend: &dyn ObsBackend) -> Result<(), RecordingError> {
+        if !backend.recording_active() {
+            return Err(RecordingError::NotRecording);
+        }
+        if !backend.recording_can_pause() {
+            return Err(RecordingError::PauseUnsupported);
+        }
+        if backend.recording_paused() {
+            return Err(RecordingError::AlreadyPaused);
+        }
+        backend.recording_pause(true);
+        self.on_paused();
+        Ok(())
+    }
+
+    /// Resumes the current recording.
+    pub fn resume(&mut self, backend: &dyn ObsBackend) -> Result<(), RecordingError> {
+        if !backend.recording_active() {
+            return Err(RecordingError::NotRecording);
+        }
+        if !backend.recording_paused() {
+            return Err(RecordingError::NotPaused);
+        }
+        backend.recording_pause(false);
+        self.on_unpaused();
+        Ok(())
+    }
+
+    /// Records the start of a pause. Pauses made from the OBS UI are reported through here too.
+    pub fn on_paused(&mut self) {
+        self.paused_since.get_or_insert_with(Instant::now);
+    }
+
+    /// Records the end of a pause.
+    pub fn on_unpaused(&mut self) {
+        if let Some(since) = self.paused_since.take() {
+            self.paused_for += since.elapsed();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_since.is_some()
+    }
+
+    /// How long the recording has been paused for, including the current pause.
+    pub fn paused_duration(&self) -> Duration {
+        self.paused_for + self.paused_since.map_or(Duration::ZERO, |since| since.elapsed())
+    }
+
+    /// How long the recording has been running for, excluding pauses.
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed().saturating_sub(self.paused_duration())
+    }
+
+    /// Marks the current time of the recording, excluding pauses.
+    pub fn add_marker(&mut self, label: &str) -> Marker {
+        let marker = Marker { label: label.to_string(), offset: self.elapsed().as_millis() as u64 };
+        self.markers.push(marker.clone());
+        marker
+    }
+
+    pub fn markers(&self) -> &[Marker] {
+        &self.markers
+    }
+
+    pub fn is_custom_name(&self) -> bool {
+        self.custom_name
+    }
+
+    pub fn recording_path(backend: &dyn ObsBackend) -> Option<String> {
+        backend.recording_output_setting("path")
+            .filter(|path| !path.is_empty())
+            .or_else(|| backend.recording_output_setting("url"))
+            .filter(|path| !path.is_empty())
+    }
+}
+
+/// Keeps track of the recording started through the API, so its name can be reverted once it stops.
+pub struct Recorder {
+    backend: Arc<dyn ObsBackend>,
+    state: Mutex<Option<RecordingState>>,
+    /// Held from the start of a recording until OBS reports it as active, or its overrides are reverted,
+    /// so a second start can't take the first one's overrides for the user's settings
+    starting: Mutex<()>,
+    /// Whether the markers are also written as FFmpeg chapters
+    ffmpeg_chapters: AtomicBool,
+    start_timeout: Duration
+}
+
+impl Recorder {
+    pub fn new(backend: Arc<dyn ObsBackend>) -> Recorder {
+        Recorder {
+            backend,
+            state: Mutex::new(None),
+            starting: Mutex::new(()),
+            ffmpeg_chapters: AtomicBool::new(false),
+            start_timeout: START_TIMEOUT
+        }
+    }
+
+    /// Sets whether the markers of the next recordings are also written as FFmpeg chapters.
+    pub fn set_ffmpeg_chapters(&self, enabled: bool) {
+        self.ffmpeg_chapters.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn backend(&self) -> &dyn ObsBackend {
+        &*self.backend
+    }
+
+    /// Starts recording with the overrides of `options`, and waits for the recording output to be active,
+    /// pausing it if requested. The overridden settings are reverted once OBS has read them, or if it doesn't start.
+    /// A recording that is still starting counts as already recording.
+    pub fn start(&self, options: &StartRequest) -> Result<(), RecordingError> {
+        let backend = self.backend();
+        let _starting = match self.starting.try_lock() {
+            Ok(guard) => guard,
+            Err(TryLockError::WouldBlock) => return Err(RecordingError::AlreadyRecording),
+            Err(TryLockError::Poisoned(e)) => e.into_inner()
+        };
+        {
+            let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+            if backend.recording_active() {
+                return Err(RecordingError::AlreadyRecording);
+            }
+            *state = Some(RecordingState::start_with(backend, options)?);
+        }
+        // OBS starts the output asynchronously, and doesn't report outputs that failed to start
+        let deadline = Instant::now() + self.start_timeout;
+        while !backend.recording_active() {
+            if Instant::now() >= deadline {
+                if let Some(mut recording) = self.state.lock().unwrap_or_else(PoisonError::into_inner).take() {
+                    recording.revert_overrides(backend);
+                }
+                return Err(RecordingError::StartFailed);
+            }
+            thread::sleep(START_POLL_INTERVAL);
+        }
+        // The output has read the overrides, which mustn't be there when OBS saves the profile config
+        if let Some(recording) = self.state.lock().unwrap_or_else(PoisonError::into_inner).as_mut() {
+            recording.revert_overrides(backend);
+        }
+        if options.pause_on_start {
+            self.pause()?;
+        }
+        Ok(())
+    }
+
+    /// Stops the current recording, returning its markers.
+    pub fn stop(&self) -> StopResponse {
+        let state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut res = RecordingState::stop(self.backend());
+        if let Some(recording) = state.as_ref() {
+            res.markers = recording.markers().to_vec();
+        }
+        res
+    }
+
+    /// Marks the current time of the recording, which might have been started from the OBS UI.
+    pub fn add_marker(&self, label: &str) -> Option<Marker> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        self.current(&mut state).map(|recording| recording.add_marker(label))
+    }
+
+    /// Pauses the current recording, which might have been started from the OBS UI.
+    pub fn pause(&self) -> Result<(), RecordingError> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        self.current(&mut state).ok_or(RecordingError::NotRecording)?.pause(self.backend())
+    }
+
+    /// Resumes the current recording.
+    pub fn resume(&self) -> Result<(), RecordingError> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        self.current(&mut state).ok_or(RecordingError::NotRecording)?.resume(self.backend())
+    }
+
+    /// Reports the state of the recording output, wherever it was started from.
+    pub fn status(&self) -> RecordingStatus {
+        let backend = self.backend();
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let stats = backend.recording_stats().unwrap_or_default();
+        match self.current(&mut state) {
+            Some(recording) => RecordingStatus {
+                active: true,
+                paused: backend.recording_paused(),
+                elapsed: recording.elapsed().as_millis() as u64,
+                path: RecordingState::recording_path(backend),
+                custom_name: recording.is_custom_name(),
+                bytes: stats.bytes,
+                dropped_frames: stats.dropped_frames,
+                total_frames: stats.total_frames
+            },
+            None => RecordingStatus::default()
+        }
+    }
+
+    /// Called when OBS reports the recording has started.
+    pub fn on_started(&self) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        // Recordings started through the API only start now
+        if let Some(recording) = self.current(&mut state) {
+            recording.started = Instant::now();
+        }
+    }
+
+    /// Called when OBS reports the recording has been paused or resumed.
+    pub fn on_paused(&self, paused: bool) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(state) = self.current(&mut state) {
+            if paused {
+                state.on_paused();
+            } else {
+                state.on_unpaused();
+            }
+        }
+    }
+
+    /// The state of the active recording, tracking recordings started from the OBS UI from now on.
+    fn current<'a>(&self, state: &'a mut Option<RecordingState>) -> Option<&'a mut RecordingState> {
+        if !self.backend.recording_active() {
+            return None;
+        }
+        Some(state.get_or_insert_with(RecordingState::new))
+    }
+
+    /// The path of the current or last recording.
+    pub fn path(&self) -> Option<String> {
+        RecordingState::recording_path(self.backend())
+    }
+
+    /// Restores the settings overridden by a recording that is still starting, e.g. before OBS exits.
+    /// The recording keeps the name and directory it was started with.
+    pub fn revert_overrides(&self) {
+        if let Some(recording) = self.state.lock().unwrap_or_else(PoisonError::into_inner).as_mut() {
+            recording.revert_overrides(self.backend());
+        }
+    }
+
+    /// Called when OBS reports the recording has stopped, writing the marker files next to it.
+    pub fn on_stopped(&self) {
+        if let Some(mut state) = self.state.lock().unwrap_or_else(PoisonError::into_inner).take() {
+            state.revert_overrides(self.backend());
+            let path = RecordingState::recording_path(self.backend()).filter(|path| !path.contains("://"));
+            if let (Some(path), false) = (path, state.markers.is_empty()) {
+                let ffmpeg_chapters = self.ffmpeg_chapters.load(Ordering::Relaxed);
+                if let Err(e) = markers::write_sidecars(Path::new(&path), &state.markers, state.elapsed(), ffmpeg_chapters) {
+                    log::warn!("Couldn't write the markers of {}: {}", path, e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::backend::ObsBackend;
+    use crate::mock::MockBackend;
+    use crate::types::{RecordingFormat, StartRequest};
+
+    use super::{RecordingError, Recorder, RecordingState};
+
+    fn named(name: &str) -> StartRequest {
+        StartRequest { name: Some(name.to_string()), ..StartRequest::default() }
+    }
+
+    #[test]
+    pub fn pause() {
+        let backend = MockBackend::new();
+        let mut recording = RecordingState::start_with(&backend, &StartRequest::default()).unwrap();
+        assert_eq!(Err(RecordingError::NotPaused), recording.resume(&backend));
+        recording.pause(&backend).unwrap();
+        assert!(backend.state().paused);
+        assert_eq!(Err(RecordingError::AlreadyPaused), recording.pause(&backend));
+        thread::sleep(Duration::from_millis(20));
+        recording.resume(&backend).unwrap();
+        assert!(!recording.is_paused());
+        let paused = recording.paused_duration();
+        assert!(paused >= Duration::from_millis(20));
+        // Durations add up across pauses
+        recording.pause(&backend).unwrap();
+        thread::sleep(Duration::from_millis(20));
+        assert!(recording.paused_duration() >= paused + Duration::from_millis(20));
+    }
+
+    #[test]
+    pub fn pause_rejected() {
+        let backend = MockBackend::new();
+        let mut recording = RecordingState::start_with(&backend, &StartRequest::default()).unwrap();
+        backend.state().can_pause = false;
+        assert_eq!(Err(RecordingError::PauseUnsupported), recording.pause(&backend));
+        RecordingState::stop(&backend);
+        assert_eq!(Err(RecordingError::NotRecording), recording.pause(&backend));
+        assert!(!recording.is_paused());
+    }
+
+    #[test]
+    pub fn start_failed() {
+        let backend = Arc::new(MockBackend::new());
+        let mut recorder = Recorder::new(Arc::clone(&backend) as Arc<dyn ObsBackend>);
+        recorder.start_timeout = Duration::from_millis(100);
+        backend.state().fail_start = true;
+        let options = StartRequest {
+            directory: Some(std::env::temp_dir().to_string_lossy().into_owned()),
+            format: Some(RecordingFormat::Mp4),
+            ..named("Match")
+        };
+        assert_eq!(Err(RecordingError::StartFailed), recorder.start(&options));
+        assert_eq!(Some("%CCYY-%MM-%DD %hh-%mm-%ss".to_string()), backend.profile_config_get("Output", "FilenameFormatting"));
+        assert_eq!(Some("/recordings".to_string()), backend.profile_config_get("SimpleOutput", "FilePath"));
+        assert_eq!(Some("mkv".to_string()), backend.profile_config_get("SimpleOutput", "RecFormat"));
+        backend.state().fail_start = false;
+        recorder.start(&named("Match")).unwrap();
+        assert_eq!(Err(RecordingError::AlreadyRecording), recorder.start(&named("Match")));
+    }
+
+    #[test]
+    pub fn concurrent_start() {
+        let backend = Arc::new(MockBackend::new());
+        let mut recorder = Recorder::new(Arc::clone(&backend) as Arc<dyn ObsBackend>);
+        recorder.start_timeout = Duration::from_millis(200);
+        let recorder = Arc::new(recorder);
+        backend.state().fail_start = true;
+        let first = {
+            let recorder = Arc::clone(&recorder);
+            thread::spawn(move || recorder.start(&named("First")))
+        };
+        thread::sleep(Duration::from_millis(50));
+        // OBS hasn't reported the first recording as active yet
+        assert_eq!(Err(RecordingError::AlreadyRecording), recorder.start(&named("Second")));
+        assert_eq!(Err(RecordingError::StartFailed), first.join().unwrap());
+        assert_eq!(Some("%CCYY-%MM-%DD %hh-%mm-%ss".to_string()), backend.profile_config_get("Output", "FilenameFormatting"));
+    }
+
+    #[test]
+    pub fn status() {
+        let backend = Arc::new(MockBackend::new());
+        let recorder = Recorder::new(Arc::clone(&backend) as Arc<dyn ObsBackend>);
+        assert!(!recorder.status().active);
+        // Started from the OBS UI
+        backend.recording_start();
+        recorder.on_started();
+        backend.state().stats.bytes = 2048;
+        thread::sleep(Duration::from_millis(20));
+        recorder.pause().unwrap();
+        let status = recorder.status();
+        assert!(status.active && status.paused && !status.custom_name);
+        assert!(status.elapsed >= 20);
+        assert_eq!(2048, status.bytes);
+        assert_eq!(Some("/recordings/%CCYY-%MM-%DD %hh-%mm-%ss.mkv".to_string()), status.path);
+        recorder.stop();
+        recorder.on_stopped();
+        recorder.start(&named("Match")).unwrap();
+        assert!(recorder.status().custom_name);
+    }
+
+    #[test]
+    pub fn overrides() {
+        let backend = Arc::new(MockBackend::new());
+        let recorder = Recorder::new(Arc::clone(&backend) as Arc<dyn ObsBackend>);
+        let dir = std::env::temp_dir().to_string_lossy().into_owned();
+        let options = StartRequest { directory: Some(dir.clone()), format: Some(RecordingFormat::Mp4), ..StartRequest::default() };
+        recorder.start(&options).unwrap();
+        assert_eq!(Some(format!("{}/%CCYY-%MM-%DD %hh-%mm-%ss.mp4", dir)), recorder.path());
+        assert!(!recorder.status().custom_name);
+        // Reverted once the output is active, so OBS can't save them to the profile
+        assert_eq!(Some("/recordings".to_string()), backend.profile_config_get("SimpleOutput", "FilePath"));
+        assert_eq!(Some("mkv".to_string()), backend.profile_config_get("SimpleOutput", "RecFormat"));
+        recorder.stop();
+        recorder.on_stopped();
+        recorder.start(&named("Match")).unwrap();
+        assert_eq!(Some("/recordings/Match.mkv".to_string()), recorder.path());
+        assert!(recorder.status().custom_name);
+        assert_eq!(Some("%CCYY-%MM-%DD %hh-%mm-%ss".to_string()), backend.profile_config_get("Output", "FilenameFormatting"));
+        recorder.stop();
+        recorder.on_stopped();
+
+        // Nothing is applied when an option is invalid
+        let options = StartRequest { directory: Some("/does/not/exist".to_string()), ..named("Match") };
+        assert_eq!(Err(RecordingError::InvalidDirectory), recorder.start(&options));
+        assert_eq!(Some("%CCYY-%MM-%DD %hh-%mm-%ss".to_string()), backend.profile_config_get("Output", "FilenameFormatting"));
+        assert!(!backend.state().recording);
+
+        // The advanced mode is configured separately
+        backend.config_insert("Output", "Mode", "Advanced");
+        backend.config_insert("AdvOut", "RecType", "FFmpeg");
+        let options = StartRequest { format: Some(RecordingFormat::Flv), ..StartRequest::default() };
+        assert_eq!(Err(RecordingError::FormatUnsupported), recorder.start(&options));
+        let options = StartRequest { directory: Some(dir.clone()), ..StartRequest::default() };
+        recorder.start(&options).unwrap();
+        assert!(recorder.path().unwrap().starts_with(&dir));
+        // Settings missing from the profile are removed again, rather than left empty
+        assert_eq!(None, backend.profile_config_get("AdvOut", "FFFilePath"));
+    }
+
+    #[test]
+    pub fn pause_on_start() {
+        let backend = Arc::new(MockBackend::new());
+        let recorder = Recorder::new(Arc::clone(&backend) as Arc<dyn ObsBackend>);
+        let options = StartRequest { pause_on_start: true, ..named("Match") };
+        backend.state().can_pause = false;
+        assert_eq!(Err(RecordingError::PauseUnsupported), recorder.start(&options));
+        assert!(!backend.state().recording);
+        backend.state().can_pause = true;
+        recorder.start(&options).unwrap();
+        assert!(backend.state().paused);
+        assert!(recorder.status().paused);
+    }
+}
@@ -0,0 +1,189 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Control of the OBS replay buffer.
+
+use std::fmt;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::backend::ObsBackend;
+use crate::events::{EventBus, EventKind};
+
+/// The replay buffer output setting holding the file name formatting.
+const FORMAT: &str = "format";
+
+/// Why the replay buffer couldn't be started, stopped or saved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayError {
+    NotActive,
+    AlreadyActive,
+    /// The file name formatting contains a NUL character, which OBS can't store.
+    InvalidName,
+    /// OBS didn't report the replay as saved in time.
+    Timeout
+}
+
+impl ReplayError {
+    /// The HTTP status reported for this error.
+    pub fn status(self) -> u16 {
+        match self {
+            ReplayError::NotActive | ReplayError::AlreadyActive => 409,
+            ReplayError::InvalidName => 400,
+            ReplayError::Timeout => 504
+        }
+    }
+
+    /// The error code reported for this error.
+    pub fn code(self) -> &'static str {
+        match self {
+            ReplayError::NotActive => "replay_buffer_inactive",
+            ReplayError::AlreadyActive => "replay_buffer_active",
+            ReplayError::InvalidName => "invalid_name",
+            ReplayError::Timeout => "timeout"
+        }
+    }
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ReplayError::NotActive => "The replay buffer is not active",
+            ReplayError::AlreadyActive => "The replay buffer is already active",
+            ReplayError::InvalidName => "The file name formatting must not contain NUL characters",
+            ReplayError::Timeout => "The replay was not saved in time"
+        })
+    }
+}
+
+pub struct ReplayBuffer {
+    backend: Arc<dyn ObsBackend>,
+    /// Held while saving, so a custom file name formatting only applies to its own save.
+    saving: Mutex<()>
+}
+
+impl ReplayBuffer {
+    pub fn new(backend: Arc<dyn ObsBackend>) -> ReplayBuffer {
+        ReplayBuffer { backend, saving: Mutex::new(()) }
+    }
+
+    pub fn start(&self) -> Result<(), ReplayError> {
+        if self.backend.replay_buffer_active() {
+            return Err(ReplayError::AlreadyActive);
+        }
+        self.backend.replay_buffer_start();
+        Ok(())
+    }
+
+    pub fn stop(&self) -> Result<(), ReplayError> {
+        if !self.backend.replay_buffer_active() {
+            return Err(ReplayError::NotActive);
+        }
+        self.backend.replay_buffer_stop();
+        Ok(())
+    }
+
+    /// The path of the last saved replay.
+    pub fn path(&self) -> Option<String> {
+        self.backend.replay_buffer_last_path().filter(|path| !path.is_empty())
+    }
+
+    /// Saves the replay buffer, waiting for OBS to report it as saved, and returns the path of the saved file.
+    /// # Arguments
+    /// * `events` - The bus OBS frontend events are published to.
+    /// * `name` - The file name format of this replay, or empty to use the default one.
+    ///   The previous format is restored once the replay is saved, or once OBS gave up.
+    /// * `timeout` - How long to wait for OBS to save the replay.
+    pub fn save(&self, events: &EventBus, name: &str, timeout: Duration) -> Result<Option<String>, ReplayError> {
+        if name.contains('\0') {
+            return Err(ReplayError::InvalidName);
+        }
+        let _saving = self.saving.lock().unwrap_or_else(PoisonError::into_inner);
+        if !self.backend.replay_buffer_active() {
+            return Err(ReplayError::NotActive);
+        }
+        let old_name = if name.is_empty() {
+            None
+        } else {
+            let old_name = self.backend.replay_buffer_setting(FORMAT).unwrap_or_default();
+            self.backend.replay_buffer_set_setting(FORMAT, name);
+            Some(old_name)
+        };
+        // Subscribe first, so the event can't be missed
+        let saved = events.subscribe();
+        self.backend.replay_buffer_save();
+        let deadline = Instant::now() + timeout;
+        let res = loop {
+            match saved.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(event) if event.kind == EventKind::ReplayBufferSaved => break Ok(event.path.clone()),
+                Ok(_) => {}
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => break Err(ReplayError::Timeout)
+            }
+        };
+        if let Some(old_name) = old_name {
+            self.backend.replay_buffer_set_setting(FORMAT, &old_name);
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::backend::ObsBackend;
+    use crate::events::{Event, EventBus, EventKind};
+    use crate::mock::MockBackend;
+
+    use super::{ReplayBuffer, ReplayError};
+
+    #[test]
+    pub fn save() {
+        let backend = Arc::new(MockBackend::new());
+        let replay = Arc::new(ReplayBuffer::new(Arc::clone(&backend) as Arc<dyn ObsBackend>));
+        let events = Arc::new(EventBus::default());
+        assert_eq!(Err(ReplayError::NotActive), replay.save(&events, "", Duration::from_secs(1)));
+        assert_eq!(Err(ReplayError::NotActive), replay.stop());
+        replay.start().unwrap();
+        assert_eq!(Err(ReplayError::AlreadyActive), replay.start());
+        {
+            let (replay, events) = (Arc::clone(&replay), Arc::clone(&events));
+            backend.state().on_replay_saved = Some(Arc::new(move || {
+                let mut event = Event::new(EventKind::ReplayBufferSaved);
+                event.path = replay.path();
+                events.publish(event);
+            }));
+        }
+        assert_eq!(Ok(Some("/replays/Goal.mkv".to_string())), replay.save(&events, "Goal", Duration::from_secs(1)));
+        assert_eq!(Some("Replay %CCYY-%MM-%DD %hh-%mm-%ss".to_string()), backend.replay_buffer_setting("format"));
+    }
+
+    #[test]
+    pub fn save_timeout() {
+        let backend = Arc::new(MockBackend::new());
+        let replay = ReplayBuffer::new(Arc::clone(&backend) as Arc<dyn ObsBackend>);
+        replay.start().unwrap();
+        // Nothing reports the replay as saved
+        let events = EventBus::default();
+        assert_eq!(Err(ReplayError::InvalidName), replay.save(&events, "Go\0al", Duration::from_millis(50)));
+        assert_eq!(Err(ReplayError::Timeout), replay.save(&events, "Goal", Duration::from_millis(50)));
+        assert_eq!(Some("Replay %CCYY-%MM-%DD %hh-%mm-%ss".to_string()), backend.replay_buffer_setting("format"));
+    }
+}
@@ -0,0 +1,195 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io;
+
+use percent_encoding::percent_decode_str;
+use tiny_http::Method;
+
+use crate::server::Responder;
+
+pub type ResCallback = Box<dyn Fn(Responder, Params) -> io::Result<()> + Send + Sync>;
+
+/// Values extracted from the request URL.
+#[derive(Debug, Default)]
+pub struct Params {
+    path: HashMap<String, String>,
+    query: HashMap<String, String>,
+}
+
+impl Params {
+    /// Gets a path parameter, e.g. `name` for `/scenes/{name}`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.path.get(name).map(String::as_str)
+    }
+
+    /// Gets a query string parameter.
+    pub fn query(&self, name: &str) -> Option<&str> {
+        self.query.get(name).map(String::as_str)
+    }
+}
+
+/// The result of looking up a request in the [`Router`].
+pub enum Match<'a> {
+    Found(&'a ResCallback, Params),
+    /// The path exists, but not with this method. Contains the allowed methods.
+    MethodNotAllowed(Vec<&'a Method>),
+    NotFound,
+}
+
+enum Segment {
+    Static(String),
+    Param(String),
+}
+
+struct Route {
+    method: Method,
+    segments: Vec<Segment>,
+    callback: ResCallback,
+}
+
+impl Route {
+    /// Extracts the path parameters if the path matches this route.
+    fn matches(&self, path: &[Cow<str>]) -> Option<HashMap<String, String>> {
+        if path.len() != self.segments.len() {
+            return None;
+        }
+        let mut params = HashMap::new();
+        for (segment, part) in self.segments.iter().zip(path) {
+            match segment {
+                Segment::Static(name) if name == part => {}
+                Segment::Static(_) => return None,
+                Segment::Param(name) => {
+                    params.insert(name.clone(), part.to_string());
+                }
+            }
+        }
+        Some(params)
+    }
+
+    fn static_segments(&self) -> usize {
+        self.segments.iter().filter(|s| matches!(s, Segment::Static(_))).count()
+    }
+}
+
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    /// Adds a route. Segments of `path` written as `{name}` match any value,
+    /// which is then available through [`Params::get`].
+    pub fn add(&mut self, method: Method, path: &str, callback: ResCallback) {
+        let segments = split_path(path).map(|segment| {
+            if segment.starts_with('{') && segment.ends_with('}') {
+                Segment::Param(segment[1..segment.len() - 1].to_string())
+            } else {
+                Segment::Static(segment.to_string())
+            }
+        }).collect();
+        self.routes.push(Route { method, segments, callback });
+    }
+
+    pub fn find(&self, method: &Method, url: &str) -> Match<'_> {
+        let (path, query) = match url.find('?') {
+            Some(i) => (&url[..i], &url[i + 1..]),
+            None => (url, "")
+        };
+        let path: Vec<_> = split_path(path).map(|s| percent_decode_str(s).decode_utf8_lossy()).collect();
+        let mut allowed = Vec::new();
+        let mut found: Option<(&Route, HashMap<String, String>)> = None;
+        for route in &self.routes {
+            let params = match route.matches(&path) {
+                Some(params) => params,
+                None => continue
+            };
+            if &route.method != method {
+                allowed.push(&route.method);
+                continue;
+            }
+            // Prefer the most specific route, e.g. `/scenes/current` over `/scenes/{name}`
+            if found.as_ref().map_or(true, |(best, _)| best.static_segments() < route.static_segments()) {
+                found = Some((route, params));
+            }
+        }
+        match found {
+            Some((route, path)) => {
+                let query = form_urlencoded::parse(query.as_bytes()).into_owned().collect();
+                Match::Found(&route.callback, Params { path, query })
+            }
+            None if !allowed.is_empty() => Match::MethodNotAllowed(allowed),
+            None => Match::NotFound
+        }
+    }
+}
+
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|s| !s.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use tiny_http::Method;
+
+    use super::{Match, Router};
+
+    fn router() -> Router {
+        let mut router = Router::default();
+        for (method, path) in [(Method::Get, "/"), (Method::Post, "/recording/start"), (Method::Get, "/scenes/{name}"),
+                               (Method::Put, "/scenes/{name}"), (Method::Get, "/scenes/current")] {
+            router.add(method, path, Box::new(move |_, _| Ok(())));
+        }
+        router
+    }
+
+    #[test]
+    pub fn params() {
+        match router().find(&Method::Get, "/scenes/Game%20Scene?studio=true&x=a+b") {
+            Match::Found(_, params) => {
+                assert_eq!(Some("Game Scene"), params.get("name"));
+                assert_eq!(Some("true"), params.query("studio"));
+                assert_eq!(Some("a b"), params.query("x"));
+                assert_eq!(None, params.query("y"));
+            }
+            _ => panic!("Route not found")
+        }
+    }
+
+    #[test]
+    pub fn specific_route() {
+        match router().find(&Method::Get, "/scenes/current") {
+            Match::Found(_, params) => assert_eq!(None, params.get("name")),
+            _ => panic!("Route not found")
+        }
+    }
+
+    #[test]
+    pub fn methods() {
+        let router = router();
+        assert!(matches!(router.find(&Method::Get, "/"), Match::Found(..)));
+        assert!(matches!(router.find(&Method::Post, "/recording/start/?x=1"), Match::Found(..)));
+        match router.find(&Method::Delete, "/scenes/Game") {
+            Match::MethodNotAllowed(allowed) => assert_eq!(vec![&Method::Get, &Method::Put], allowed),
+            _ => panic!("Method should not be allowed")
+        }
+        assert!(matches!(router.find(&Method::Get, "/recording"), Match::NotFound));
+    }
+}
@@ -0,0 +1,1110 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::io::{ErrorKind, Read};
+use std::sync::{Arc, Mutex, TryLockError};
+use std::time::Duration;
+
+use serde::Serialize;
+use tiny_http::{Header, Method, Response};
+use uuid::Uuid;
+
+use crate::Controller;
+use crate::recording::RecordingError;
+use crate::router::Params;
+use crate::screenshot::{self, MAX_SIZE};
+use crate::server::{self, HttpServer};
+use crate::types::{
+    AppRegistrationData, AppsResponse, ErrorResponse, InfoResponse, MarkerRequest, MuteRequest, NameRequest, ReplayRequest, ReplayResponse,
+    SceneResponse, RegisteredApp, ScreenshotResponse, SourcesResponse, StartRequest, VisibilityRequest, Volume,
+};
+use crate::verification::{self, VerificationResult};
+use crate::{sse, websocket};
+
+macro_rules! validate_input {
+    ($condition: expr, $error: literal, $desc: literal, $code: literal) => {
+        if !($condition) {
+            return (
+                $code,
+                Cow::Borrowed(concat!(r#"{"error": ""#, $error, r#"", "message": ""#, $desc, r#""}"#)),
+            );
+        }
+    };
+}
+
+const INVALID_JSON: &str = "Invalid json data, check the docs for a valid schema";
+
+const MAX_MARKER_LABEL: usize = 100;
+
+/// A status and JSON body.
+type JsonResponse = (u16, Cow<'static, str>);
+
+/// How long `/replay/save` waits for OBS to write the replay.
+const REPLAY_SAVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Adds the info, registration, output control, scene and event routes, backed by the given controller.
+pub fn add_routes(server: &mut HttpServer, controller: Arc<Controller>) {
+    let info = serde_json::to_string(&InfoResponse {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        obs: controller.recorder.backend().version(),
+    }).unwrap();
+    server.add_route(
+        Method::Get,
+        "/",
+        Box::new(move |req, _| req.respond(server::json_response(200, &info))),
+    );
+    let register = Arc::clone(&controller);
+    // Only one approval dialog is shown at a time, so pending registrations can't take up every worker
+    let pending = Mutex::new(());
+    server.add_route(Method::Post, "/register", Box::new(move |mut req, _| {
+        let (status, res): (u16, Cow<str>) = (|| {
+            let _pending = match pending.try_lock() {
+                Ok(guard) => guard,
+                Err(TryLockError::WouldBlock) => return error(429, "registration_pending", "Another registration is pending"),
+                Err(TryLockError::Poisoned(e)) => e.into_inner()
+            };
+            let mut body = String::with_capacity(1024.min(req.body_length().unwrap_or(1024)));
+            let body_res = req.as_reader().take(1024).read_to_string(&mut body);
+            validate_input!(body_res.is_ok() && !body.is_empty(), "invalid_body", "Body cannot be empty", 400);
+            let data = match serde_json::from_str::<AppRegistrationData>(&body) {
+                Ok(data) => data,
+                Err(_) => return error(400, "invalid_json", INVALID_JSON)
+            };
+            validate_input!(data.uuid.len() == 36 || data.uuid.len() == 32, "invalid_uuid", "Invalid UUID. Only stripped and hyphenated UUIDs are supported.", 400);
+            validate_input!(!data.name.is_empty() && data.name.len() <= 24, "invalid_name", "Name length must be within (0;24]", 400);
+            validate_input!(!data.name.contains('\0'), "invalid_name", "Name must not contain NUL characters", 400);
+            validate_input!(data.version == 1 || data.version == verification::REGISTRATION_VERSION, "unsupported_version",
+                "Unsupported registration protocol version", 400);
+            let uuid = match Uuid::parse_str(&data.uuid) {
+                Ok(uuid) => uuid,
+                Err(_) => return error(400, "invalid_uuid", "Invalid UUID")
+            };
+            let bytes: [u8; 32] = match base64::decode(&data.public_key).map(<[u8; 32]>::try_from) {
+                Ok(Ok(bytes)) => bytes,
+                Ok(Err(_)) => return error(400, "invalid_public_key", "Public key must be 32 bytes in length"),
+                Err(_) => return error(400, "invalid_public_key", "Invalid Base64")
+            };
+            let pub_key = x25519_dalek::PublicKey::from(bytes);
+            if !register.approver.approve(&data.name) {
+                return error(401, "registration_denied", "The registration request was denied by the user");
+            }
+            match verification::register_encrypt(&register.apps, uuid, data.name, pub_key, data.version) {
+                Ok(res) => json(&res),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => error(409, "already_registered", "An app with the same UUID already exists"),
+                Err(e) => {
+                    log::error!("User request refused due to an error {:?}", e);
+                    error(500, "internal_error", "The request couldn't be fulfilled due to an error")
+                }
+            }
+        })();
+        req.respond(server::json_response(status, &res))
+    }));
+    add_admin_route(server, Method::Get, "/apps", &controller, |controller, _| {
+        match controller.apps.list() {
+            Ok(apps) => json(&AppsResponse {
+                apps: apps.iter().map(|app| RegisteredApp {
+                    uuid: app.uuid().to_hyphenated().to_string(),
+                    name: app.name().to_string(),
+                    registered: app.registered(),
+                }).collect()
+            }),
+            Err(e) => {
+                log::error!("Couldn't read the app store {:?}", e);
+                error(500, "internal_error", "The request couldn't be fulfilled due to an error")
+            }
+        }
+    });
+    add_admin_route(server, Method::Delete, "/apps/{app}", &controller, |controller, params| {
+        let uuid = match Uuid::parse_str(params.get("app").unwrap_or_default()) {
+            Ok(uuid) => verification::uuid_to_u128(uuid),
+            Err(_) => return error(400, "invalid_uuid", "Invalid UUID")
+        };
+        match controller.apps.revoke(uuid) {
+            Ok(true) => (200, Cow::Borrowed(r#"{"message": "App revoked"}"#)),
+            Ok(false) => error(404, "unknown_app", "Unknown app"),
+            Err(e) => {
+                log::error!("Couldn't rewrite the app store {:?}", e);
+                error(500, "internal_error", "The request couldn't be fulfilled due to an error")
+            }
+        }
+    });
+    let start = Arc::clone(&controller);
+    server.add_route(Method::Post, "/recording/start", Box::new(move |mut req, _| {
+        let json_content = req.headers().iter().any(|h| h.field.equiv("Content-Type")
+            && h.value.as_str().trim_start().to_ascii_lowercase().starts_with("application/json"));
+        let (status, msg) = match verification::middleware_auth(&mut req, &start.apps) {
+            VerificationResult::Body(body) => match start_options(&body, json_content) {
+                Ok(options) => match start.recorder.start(&options) {
+                    Ok(()) => (200, Cow::Borrowed(r#"{"message": "Recording started"}"#)),
+                    Err(e) => error(e.status(), e.code(), e)
+                },
+                Err(res) => res
+            },
+            VerificationResult::JsonReject(status, msg) => (status, Cow::Borrowed(msg)),
+        };
+        req.respond(server::json_response(status, &msg))
+    }));
+    add_signed_route(server, Method::Post, "/recording/stop", &controller, |controller, _, _| {
+        json(&controller.recorder.stop())
+    });
+    add_signed_route(server, Method::Post, "/recording/marker", &controller, |controller, body, _| {
+        let marker = match serde_json::from_str::<MarkerRequest>(&body) {
+            Ok(marker) => marker,
+            Err(_) => return error(400, "invalid_json", INVALID_JSON)
+        };
+        if marker.label.is_empty() || marker.label.chars().count() > MAX_MARKER_LABEL {
+            return error(400, "invalid_label", format!("Label length must be within (0;{}]", MAX_MARKER_LABEL));
+        }
+        match controller.recorder.add_marker(&marker.label) {
+            Some(marker) => json(&marker),
+            None => error(409, RecordingError::NotRecording.code(), RecordingError::NotRecording)
+        }
+    });
+    add_signed_route(server, Method::Get, "/recording/status", &controller, |controller, _, _| {
+        json(&controller.recorder.status())
+    });
+    add_signed_route(server, Method::Post, "/recording/pause", &controller, |controller, _, _| {
+        match controller.recorder.pause() {
+            Ok(()) => (200, Cow::Borrowed(r#"{"message": "Recording paused"}"#)),
+            Err(e) => error(e.status(), e.code(), e)
+        }
+    });
+    add_signed_route(server, Method::Post, "/recording/resume", &controller, |controller, _, _| {
+        match controller.recorder.resume() {
+            Ok(()) => (200, Cow::Borrowed(r#"{"message": "Recording resumed"}"#)),
+            Err(e) => error(e.status(), e.code(), e)
+        }
+    });
+    add_signed_route(server, Method::Post, "/streaming/start", &controller, |controller, _, _| {
+        if !controller.streamer.start() {
+            return error(409, "already_streaming", "Already streaming");
+        }
+        (200, Cow::Borrowed(r#"{"message": "Streaming started"}"#))
+    });
+    add_signed_route(server, Method::Post, "/streaming/stop", &controller, |controller, _, _| {
+        if !controller.streamer.stop() {
+            return error(409, "not_streaming", "Not streaming");
+        }
+        (200, Cow::Borrowed(r#"{"message": "Streaming stopped"}"#))
+    });
+    add_signed_route(server, Method::Get, "/streaming/status", &controller, |controller, _, _| {
+        json(&controller.streamer.status())
+    });
+    add_signed_route(server, Method::Post, "/replay/start", &controller, |controller, _, _| {
+        match controller.replay.start() {
+            Ok(()) => (200, Cow::Borrowed(r#"{"message": "Replay buffer started"}"#)),
+            Err(e) => error(e.status(), e.code(), e)
+        }
+    });
+    add_signed_route(server, Method::Post, "/replay/stop", &controller, |controller, _, _| {
+        match controller.replay.stop() {
+            Ok(()) => (200, Cow::Borrowed(r#"{"message": "Replay buffer stopped"}"#)),
+            Err(e) => error(e.status(), e.code(), e)
+        }
+    });
+    add_signed_route(server, Method::Post, "/replay/save", &controller, |controller, body, _| {
+        let options = if body.trim().is_empty() {
+            ReplayRequest::default()
+        } else {
+            match serde_json::from_str::<ReplayRequest>(&body) {
+                Ok(options) => options,
+                Err(_) => return error(400, "invalid_json", INVALID_JSON)
+            }
+        };
+        match controller.replay.save(&controller.events, options.name.as_deref().unwrap_or_default(), REPLAY_SAVE_TIMEOUT) {
+            Ok(path) => json(&ReplayResponse { path }),
+            Err(e) => error(e.status(), e.code(), e)
+        }
+    });
+    add_signed_route(server, Method::Get, "/scenes", &controller, |controller, _, _| {
+        json(&controller.scenes.list())
+    });
+    add_signed_route(server, Method::Get, "/scenes/current", &controller, |controller, _, _| {
+        json(&SceneResponse { name: controller.scenes.current() })
+    });
+    add_signed_route(server, Method::Put, "/scenes/current", &controller, |controller, body, _| {
+        let scene = match serde_json::from_str::<NameRequest>(&body) {
+            Ok(scene) => scene,
+            Err(_) => return error(400, "invalid_json", INVALID_JSON)
+        };
+        match controller.scenes.set_current(&scene.name) {
+            Ok(()) => (200, Cow::Borrowed(r#"{"message": "Scene changed"}"#)),
+            Err(e) => error(e.status(), e.code(), e)
+        }
+    });
+    add_signed_route(server, Method::Get, "/scenes/preview", &controller, |controller, _, _| {
+        match controller.scenes.preview() {
+            Ok(name) => json(&SceneResponse { name }),
+            Err(e) => error(e.status(), e.code(), e)
+        }
+    });
+    add_signed_route(server, Method::Put, "/scenes/preview", &controller, |controller, body, _| {
+        let scene = match serde_json::from_str::<NameRequest>(&body) {
+            Ok(scene) => scene,
+            Err(_) => return error(400, "invalid_json", INVALID_JSON)
+        };
+        match controller.scenes.set_preview(&scene.name) {
+            Ok(()) => (200, Cow::Borrowed(r#"{"message": "Preview scene changed"}"#)),
+            Err(e) => error(e.status(), e.code(), e)
+        }
+    });
+    add_signed_route(server, Method::Get, "/scenes/{scene}/sources", &controller, |controller, _, params| {
+        match controller.sources.list(params.get("scene").unwrap_or_default()) {
+            Ok(sources) => json(&SourcesResponse { sources }),
+            Err(e) => error(e.status(), e.code(), e)
+        }
+    });
+    add_signed_route(server, Method::Put, "/scenes/{scene}/sources/{source}/visible", &controller, |controller, body, params| {
+        let visibility = match serde_json::from_str::<VisibilityRequest>(&body) {
+            Ok(visibility) => visibility,
+            Err(_) => return error(400, "invalid_json", INVALID_JSON)
+        };
+        let (scene, source) = (params.get("scene").unwrap_or_default(), params.get("source").unwrap_or_default());
+        match controller.sources.set_visible(scene, source, visibility.visible) {
+            Ok(()) => (200, Cow::Borrowed(r#"{"message": "Visibility changed"}"#)),
+            Err(e) => error(e.status(), e.code(), e)
+        }
+    });
+    add_signed_route(server, Method::Get, "/sources/{source}/audio", &controller, |controller, _, params| {
+        match controller.sources.audio(params.get("source").unwrap_or_default()) {
+            Ok(audio) => json(&audio),
+            Err(e) => error(e.status(), e.code(), e)
+        }
+    });
+    add_signed_route(server, Method::Put, "/sources/{source}/mute", &controller, |controller, body, params| {
+        let mute = match serde_json::from_str::<MuteRequest>(&body) {
+            Ok(mute) => mute,
+            Err(_) => return error(400, "invalid_json", INVALID_JSON)
+        };
+        match controller.sources.set_muted(params.get("source").unwrap_or_default(), mute.muted) {
+            Ok(()) => (200, Cow::Borrowed(r#"{"message": "Mute changed"}"#)),
+            Err(e) => error(e.status(), e.code(), e)
+        }
+    });
+    add_signed_route(server, Method::Put, "/sources/{source}/volume", &controller, |controller, body, params| {
+        let volume = match serde_json::from_str::<Volume>(&body) {
+            Ok(volume) => volume,
+            Err(_) => return error(400, "invalid_json", INVALID_JSON)
+        };
+        match controller.sources.set_volume(params.get("source").unwrap_or_default(), &volume) {
+            Ok(()) => (200, Cow::Borrowed(r#"{"message": "Volume changed"}"#)),
+            Err(e) => error(e.status(), e.code(), e)
+        }
+    });
+    add_signed_route(server, Method::Get, "/scene-collections", &controller, |controller, _, _| {
+        json(&controller.scenes.collections())
+    });
+    add_signed_route(server, Method::Put, "/scene-collections/current", &controller, |controller, body, _| {
+        let collection = match serde_json::from_str::<NameRequest>(&body) {
+            Ok(collection) => collection,
+            Err(_) => return error(400, "invalid_json", INVALID_JSON)
+        };
+        match controller.scenes.set_collection(&collection.name) {
+            Ok(()) => (200, Cow::Borrowed(r#"{"message": "Scene collection changed"}"#)),
+            Err(e) => error(e.status(), e.code(), e)
+        }
+    });
+    let screenshot = Arc::clone(&controller);
+    server.add_route(Method::Get, "/screenshot", Box::new(move |mut req, params| {
+        let (status, msg) = match verification::middleware_auth(&mut req, &screenshot.apps) {
+            VerificationResult::Body(_) => match take_screenshot(&screenshot, &params) {
+                Ok((png, width, height)) if params.query("format") == Some("base64") => {
+                    json(&ScreenshotResponse { width, height, image: base64::encode(&png) })
+                }
+                Ok((png, _, _)) => {
+                    let content_type = Header::from_bytes(&b"Content-Type"[..], &b"image/png"[..]).unwrap();
+                    return req.respond(Response::from_data(png).with_header(content_type));
+                }
+                Err(res) => res
+            },
+            VerificationResult::JsonReject(status, msg) => (status, Cow::Borrowed(msg)),
+        };
+        req.respond(server::json_response(status, &msg))
+    }));
+    let events = Arc::clone(&controller);
+    server.add_route(
+        Method::Get,
+        "/events",
+        Box::new(move |req, _| websocket::serve_events(req, Arc::clone(&events))),
+    );
+    let events = controller;
+    server.add_route(
+        Method::Get,
+        "/events/sse",
+        Box::new(move |req, _| sse::serve_events(req, Arc::clone(&events))),
+    );
+}
+
+/// Adds a route only available to registered apps.
+/// `handler` gets the verified body and the path parameters, and returns the status and JSON response.
+fn add_signed_route<F>(server: &mut HttpServer, method: Method, path: &str, controller: &Arc<Controller>, handler: F)
+where F: Fn(&Controller, String, Params) -> JsonResponse + Send + Sync + 'static {
+    let controller = Arc::clone(controller);
+    server.add_route(method, path, Box::new(move |mut req, params| {
+        let (status, msg) = match verification::middleware_auth(&mut req, &controller.apps) {
+            VerificationResult::Body(body) => handler(&controller, body, params),
+            VerificationResult::JsonReject(status, msg) => (status, Cow::Borrowed(msg)),
+        };
+        req.respond(server::json_response(status, &msg))
+    }));
+}
+
+/// Adds a signed route only available to the apps of `admin_apps` in the config,
+/// which must sign with the current protocol even if legacy signatures are accepted.
+fn add_admin_route<F>(server: &mut HttpServer, method: Method, path: &str, controller: &Arc<Controller>, handler: F)
+where F: Fn(&Controller, Params) -> JsonResponse + Send + Sync + 'static {
+    let controller = Arc::clone(controller);
+    let admins: Vec<u128> = server.config().admin_apps.iter().filter_map(|uuid| match Uuid::parse_str(uuid) {
+        Ok(uuid) => Some(verification::uuid_to_u128(uuid)),
+        Err(_) => {
+            log::warn!("Ignoring invalid admin app UUID {}", uuid);
+            None
+        }
+    }).collect();
+    server.add_route(method, path, Box::new(move |mut req, params| {
+        if verification::is_legacy_signature(&req) {
+            let (status, msg) = error(401, "legacy_signature", "Requests must be signed with protocol version 2");
+            return req.respond(server::json_response(status, &msg));
+        }
+        let (status, msg) = match verification::middleware_auth(&mut req, &controller.apps) {
+            VerificationResult::Body(_) if verification::request_app(&req).map_or(true, |app| !admins.contains(&app)) => {
+                error(403, "forbidden", "This app isn't allowed to manage apps")
+            }
+            VerificationResult::Body(_) => handler(&controller, params),
+            VerificationResult::JsonReject(status, msg) => (status, Cow::Borrowed(msg)),
+        };
+        req.respond(server::json_response(status, &msg))
+    }));
+}
+
+/// Reads the body of `/recording/start`, which is a [`StartRequest`] if it is sent as `application/json`
+/// or is a JSON object. Otherwise, it is the file name formatting, as sent by older clients.
+fn start_options(body: &str, json_content: bool) -> Result<StartRequest, JsonResponse> {
+    if body.trim().is_empty() {
+        return Ok(StartRequest::default());
+    }
+    let object = || serde_json::from_str::<serde_json::Value>(body).map_or(false, |value| value.is_object());
+    if !json_content && !object() {
+        return Ok(StartRequest { name: Some(body.to_string()), ..StartRequest::default() });
+    }
+    match serde_json::from_str::<StartRequest>(body) {
+        Ok(options) if options.version == StartRequest::VERSION => Ok(options),
+        Ok(options) => Err(error(400, "unsupported_version", format!("Unsupported start request version {}, expected {}",
+                                                             options.version, StartRequest::VERSION))),
+        Err(e) => Err(error(400, "invalid_json", format!("Invalid start request: {}", e)))
+    }
+}
+
+/// Renders the frame requested by the `source`, `width` and `height` query parameters,
+/// returning the PNG image and its size.
+fn take_screenshot(controller: &Controller, params: &Params) -> Result<(Vec<u8>, u32, u32), JsonResponse> {
+    let format = params.query("format").unwrap_or("png");
+    if format != "png" && format != "base64" {
+        return Err(error(400, "invalid_format", "The format must be either png or base64"));
+    }
+    let dimension = |name| params.query(name).map(|value| value.parse::<u32>().ok().filter(|&v| v > 0 && v <= MAX_SIZE));
+    let size = match (dimension("width"), dimension("height")) {
+        (None, None) => None,
+        (Some(Some(width)), Some(Some(height))) => Some((width, height)),
+        _ => return Err(error(400, "invalid_size", format!("Width and height must be set together, within [1;{}]", MAX_SIZE)))
+    };
+    let frame = controller.frames.capture(params.query("source"), size).map_err(|e| error(e.status(), e.code(), e))?;
+    match screenshot::encode_png(&frame) {
+        Ok(png) => Ok((png, frame.width, frame.height)),
+        Err(e) => {
+            log::error!("Couldn't encode screenshot {:?}", e);
+            Err(error(500, "capture_failed", "The frame couldn't be encoded"))
+        }
+    }
+}
+
+fn json<T: Serialize>(res: &T) -> JsonResponse {
+    (200, Cow::Owned(serde_json::to_string(res).unwrap()))
+}
+
+fn error(status: u16, code: &str, message: impl ToString) -> JsonResponse {
+    let res = ErrorResponse { error: code.to_string(), message: message.to_string() };
+    (status, Cow::Owned(serde_json::to_string(&res).unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpStream;
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    use ed25519_dalek::{Keypair, Signer};
+    use rand_core::{OsRng, RngCore};
+    use sha2::{Digest, Sha256};
+    use tiny_http::Server;
+    use uuid::Uuid;
+
+    use crate::backend::{AudioState, ObsBackend};
+    use crate::events::EventKind;
+    use crate::mock::MockBackend;
+    use crate::server::HttpServer;
+    use crate::types::{AppsResponse, RegistrationResponse, SceneItem};
+    use crate::verification::tests::{temp_store, TempFile};
+    use crate::verification::{self, uuid_to_u128};
+    use crate::{Approver, Config, Controller};
+
+    use super::add_routes;
+
+    const APP: &str = "98704291-09e9-40f2-8476-064521fadaff";
+
+    struct TestServer {
+        controller: Arc<Controller>,
+        port: u16,
+        key: Keypair,
+        _store: TempFile,
+    }
+
+    fn spawn(backend: Arc<MockBackend>) -> TestServer {
+        spawn_with(backend, Box::new(|name: &str| name != "Denied"), Config::default())
+    }
+
+    fn spawn_with(backend: Arc<MockBackend>, approver: Box<dyn Approver>, config: Config) -> TestServer {
+        let (apps, store) = temp_store();
+        let (_, key) = apps.register(uuid_to_u128(Uuid::parse_str(APP).unwrap()), "Test".to_string()).unwrap();
+        let frames = Arc::clone(&backend);
+        let controller = Arc::new(Controller::new(backend, apps, approver).with_frames(frames));
+        let listener = Server::http("127.0.0.1:0").unwrap();
+        let port = listener.server_addr().port();
+        let routes = Arc::clone(&controller);
+        thread::spawn(move || {
+            let mut server = HttpServer::new(config);
+            add_routes(&mut server, routes);
+            server.serve(listener)
+        });
+        TestServer { controller, port, key, _store: store }
+    }
+
+    /// A random `X-OBSC-Nonce`.
+    fn nonce() -> String {
+        let mut nonce = [0u8; 16];
+        OsRng.fill_bytes(&mut nonce);
+        base64::encode(nonce)
+    }
+
+    fn request(port: u16, path: &str, headers: &[(&str, String)], body: &str) -> (u16, String) {
+        send(port, "POST", path, headers, body)
+    }
+
+    fn send(port: u16, method: &str, path: &str, headers: &[(&str, String)], body: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        write!(stream, "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n", method, path, body.len()).unwrap();
+        for (name, value) in headers {
+            write!(stream, "{}: {}\r\n", name, value).unwrap();
+        }
+        write!(stream, "\r\n{}", body).unwrap();
+        let mut res = String::new();
+        stream.read_to_string(&mut res).unwrap();
+        let status = res[9..12].parse().unwrap();
+        let body = res.split_once("\r\n\r\n").map(|(_, body)| body).unwrap_or_default().to_string();
+        (status, body)
+    }
+
+    impl TestServer {
+        fn signature(&self, body: &str) -> String {
+            let mut hash = Sha256::new();
+            hash.update(if body.is_empty() { "obs-controller" } else { body }.as_bytes());
+            base64::encode(self.key.sign(&hash.finalize()[..]).to_bytes())
+        }
+
+        /// The headers of a request signed with the current protocol, including its `Content-Type` if it has one.
+        fn signed_headers(&self, method: &str, path: &str, content_type: Option<&str>, body: &str, timestamp: u64,
+                          nonce: &str) -> Vec<(&'static str, String)> {
+            let mut headers = vec![
+                ("X-OBSC-App", APP.to_string()),
+                ("X-OBSC-Version", "2".to_string()),
+                ("X-OBSC-Timestamp", timestamp.to_string()),
+                ("X-OBSC-Nonce", nonce.to_string()),
+            ];
+            if let Some(content_type) = content_type {
+                headers.push(("Content-Type", content_type.to_string()));
+            }
+            let signed: Vec<_> = headers.iter().map(|(name, value)| (*name, value.as_str())).collect();
+            let mut hash = Sha256::new();
+            hash.update(verification::canonical_request(method, path, &signed, body).as_bytes());
+            headers.push(("X-OBSC-Signature", base64::encode(self.key.sign(&hash.finalize()[..]).to_bytes())));
+            headers
+        }
+
+        /// The headers of a bodyless request signed now, as raw header lines.
+        fn signed_lines(&self, method: &str, path: &str) -> String {
+            self.signed_headers(method, path, None, "", verification::unix_time(), &nonce()).iter()
+                .map(|(name, value)| format!("{}: {}\r\n", name, value))
+                .collect()
+        }
+
+        fn send_signed(&self, method: &str, path: &str, body: &str) -> (u16, String) {
+            let headers = self.signed_headers(method, path, None, body, verification::unix_time(), &nonce());
+            send(self.port, method, path, &headers, body)
+        }
+
+        fn signed(&self, path: &str, body: &str) -> (u16, String) {
+            self.send_signed("POST", path, body)
+        }
+
+        /// A signed POST request with an `application/json` body.
+        fn signed_json(&self, path: &str, body: &str) -> (u16, String) {
+            let headers = self.signed_headers("POST", path, Some("application/json"), body, verification::unix_time(), &nonce());
+            send(self.port, "POST", path, &headers, body)
+        }
+
+        fn signed_get(&self, path: &str) -> (u16, String) {
+            self.send_signed("GET", path, "")
+        }
+
+        fn signed_put(&self, path: &str, body: &str) -> (u16, String) {
+            self.send_signed("PUT", path, body)
+        }
+    }
+
+    #[test]
+    pub fn start_stop() {
+        let backend = Arc::new(MockBackend::new());
+        let server = spawn(Arc::clone(&backend));
+        assert_eq!(200, server.signed("/recording/start", "").0);
+        assert!(backend.state().recording);
+        let (status, body) = server.signed("/recording/stop", "");
+        assert_eq!(200, status);
+        assert!(!backend.state().recording);
+        let res: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!("/recordings/%CCYY-%MM-%DD %hh-%mm-%ss.mkv", res["path"]);
+    }
+
+    #[test]
+    pub fn start_with_name() {
+        let backend = Arc::new(MockBackend::new());
+        let server = spawn(Arc::clone(&backend));
+        assert_eq!(200, server.signed("/recording/start", r#"{"version": 1, "name": "Match 1"}"#).0);
+        let (_, body) = server.signed("/recording/stop", "");
+        let res: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!("/recordings/Match 1.mkv", res["path"]);
+        // OBS notifies us once the output has stopped
+        server.controller.recorder.on_stopped();
+        assert_eq!(Some("%CCYY-%MM-%DD %hh-%mm-%ss".to_string()),
+                   backend.profile_config_get("Output", "FilenameFormatting"));
+    }
+
+    #[test]
+    pub fn start_options() {
+        let backend = Arc::new(MockBackend::new());
+        let server = spawn(Arc::clone(&backend));
+        let dir = std::env::temp_dir().to_string_lossy().into_owned();
+        let body = serde_json::json!({"name": "Match 1", "directory": dir, "format": "flv"}).to_string();
+        assert_eq!(200, server.signed_json("/recording/start", &body).0);
+        let (_, body) = server.signed("/recording/stop", "");
+        let res: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(format!("{}/Match 1.flv", dir), res["path"]);
+        server.controller.recorder.on_stopped();
+        assert_eq!(Some("mkv".to_string()), backend.profile_config_get("SimpleOutput", "RecFormat"));
+
+        assert_eq!(400, server.signed_json("/recording/start", r#"{"directory": "/does/not/exist"}"#).0);
+        let (status, body) = server.signed_json("/recording/start", r#"{"format": "avi"}"#);
+        assert_eq!(400, status);
+        assert!(body.contains("unknown variant `avi`"));
+        assert!(server.signed_json("/recording/start", r#"{"nmae": "Match"}"#).1.contains("unknown field `nmae`"));
+        // Objects are JSON whatever their content type
+        assert!(server.signed("/recording/start", r#"{"version": 2}"#).1.contains("Unsupported start request version 2"));
+        assert!(server.signed("/recording/start", r#"{"nmae": "Match"}"#).1.contains("unknown field `nmae`"));
+        assert_eq!(400, server.signed_json("/recording/start", "Match 1").0);
+        assert!(!backend.state().recording);
+    }
+
+    #[test]
+    pub fn plain_text_start() {
+        let backend = Arc::new(MockBackend::new());
+        let server = spawn(Arc::clone(&backend));
+        // Old clients send the file name formatting as is
+        assert_eq!(200, server.signed("/recording/start", "Match 1").0);
+        assert_eq!(Some("/recordings/Match 1.mkv".to_string()), server.controller.recorder.path());
+        server.signed("/recording/stop", "");
+        server.controller.recorder.on_stopped();
+        assert_eq!(200, server.signed("/recording/start", r#"{"name": "Match"}"#).0);
+        assert_eq!(Some("/recordings/Match.mkv".to_string()), server.controller.recorder.path());
+        server.signed("/recording/stop", "");
+        server.controller.recorder.on_stopped();
+        // Other JSON values are names
+        assert_eq!(200, server.signed("/recording/start", r#""Match 2""#).0);
+        assert_eq!(Some(r#"/recordings/"Match 2".mkv"#.to_string()), server.controller.recorder.path());
+    }
+
+    #[test]
+    pub fn pause_resume() {
+        let backend = Arc::new(MockBackend::new());
+        let server = spawn(Arc::clone(&backend));
+        let (status, body) = server.signed("/recording/pause", "");
+        assert_eq!(409, status);
+        assert_eq!(r#"{"error":"not_recording","message":"Not recording"}"#, body);
+        // Recordings started from the OBS UI can be paused too
+        backend.recording_start();
+        assert_eq!(200, server.signed("/recording/pause", "").0);
+        assert!(backend.state().paused);
+        assert_eq!(409, server.signed("/recording/pause", "").0);
+        assert_eq!(200, server.signed("/recording/resume", "").0);
+        assert!(!backend.state().paused);
+        assert_eq!(409, server.signed("/recording/resume", "").0);
+        backend.state().can_pause = false;
+        let (status, body) = server.signed("/recording/pause", "");
+        assert_eq!(409, status);
+        assert_eq!(r#"{"error":"pause_unsupported","message":"The recording output does not support pausing"}"#, body);
+    }
+
+    #[test]
+    pub fn status() {
+        let backend = Arc::new(MockBackend::new());
+        let server = spawn(Arc::clone(&backend));
+        let (status, body) = server.signed_get("/recording/status");
+        assert_eq!(200, status);
+        let res: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(false, res["active"]);
+        assert_eq!(200, server.signed_json("/recording/start", r#"{"name": "Match 1"}"#).0);
+        backend.state().stats.dropped_frames = 3;
+        let res: serde_json::Value = serde_json::from_str(&server.signed_get("/recording/status").1).unwrap();
+        assert_eq!(true, res["active"]);
+        assert_eq!(false, res["paused"]);
+        assert_eq!(true, res["custom_name"]);
+        assert_eq!("/recordings/Match 1.mkv", res["path"]);
+        assert_eq!(3, res["dropped_frames"]);
+        assert_eq!(400, send(server.port, "GET", "/recording/status", &[], "").0);
+    }
+
+    #[test]
+    pub fn streaming() {
+        let backend = Arc::new(MockBackend::new());
+        let server = spawn(Arc::clone(&backend));
+        let res: serde_json::Value = serde_json::from_str(&server.signed_get("/streaming/status").1).unwrap();
+        assert_eq!(false, res["active"]);
+        assert_eq!(200, server.signed("/streaming/start", "").0);
+        assert!(backend.state().streaming);
+        let (status, body) = server.signed("/streaming/start", "");
+        assert_eq!(409, status);
+        assert!(body.contains("already_streaming"));
+        server.controller.handle_event(EventKind::StreamingStarted);
+        {
+            let mut state = backend.state();
+            state.reconnecting = true;
+            state.congestion = 0.5;
+        }
+        thread::sleep(Duration::from_millis(20));
+        let res: serde_json::Value = serde_json::from_str(&server.signed_get("/streaming/status").1).unwrap();
+        assert_eq!(true, res["active"]);
+        assert_eq!(true, res["reconnecting"]);
+        assert_eq!(0.5, res["congestion"]);
+        assert!(res["uptime"].as_u64().unwrap() >= 20);
+        assert_eq!(200, server.signed("/streaming/stop", "").0);
+        assert!(!backend.state().streaming);
+        let (status, body) = server.signed("/streaming/stop", "");
+        assert_eq!(409, status);
+        assert!(body.contains("not_streaming"));
+        assert_eq!(400, send(server.port, "GET", "/streaming/status", &[], "").0);
+    }
+
+    #[test]
+    pub fn scenes() {
+        let backend = Arc::new(MockBackend::new());
+        {
+            let mut state = backend.state();
+            state.scenes = vec!["Lobby".to_string(), "Game".to_string()];
+            state.scene = Some("Lobby".to_string());
+        }
+        let server = spawn(Arc::clone(&backend));
+        let res: serde_json::Value = serde_json::from_str(&server.signed_get("/scenes").1).unwrap();
+        assert_eq!(serde_json::json!(["Lobby", "Game"]), res["scenes"]);
+        assert_eq!("Lobby", res["current"]);
+        assert_eq!(200, server.signed_put("/scenes/current", r#"{"name": "Game"}"#).0);
+        assert_eq!(r#"{"name":"Game"}"#, server.signed_get("/scenes/current").1);
+        let (status, body) = server.signed_put("/scenes/current", r#"{"name": "Menu"}"#);
+        assert_eq!(404, status);
+        assert_eq!(r#"{"error":"unknown_scene","message":"No scene with this name"}"#, body);
+        assert_eq!(400, server.signed_put("/scenes/current", r#"{"name": ""}"#).0);
+        let (status, body) = server.signed_put("/scenes/current", "Game");
+        assert_eq!(400, status);
+        assert!(body.contains("invalid_json"));
+        // The preview scene needs studio mode
+        assert_eq!(409, server.signed_put("/scenes/preview", r#"{"name": "Lobby"}"#).0);
+        backend.state().studio_mode = true;
+        assert_eq!(200, server.signed_put("/scenes/preview", r#"{"name": "Lobby"}"#).0);
+        let res: serde_json::Value = serde_json::from_str(&server.signed_get("/scenes").1).unwrap();
+        assert_eq!("Game", res["current"]);
+        assert_eq!("Lobby", res["preview"]);
+        assert_eq!(400, send(server.port, "GET", "/scenes", &[], "").0);
+    }
+
+    #[test]
+    pub fn sources() {
+        let backend = Arc::new(MockBackend::new());
+        {
+            let mut state = backend.state();
+            state.scenes = vec!["Game".to_string()];
+            state.scene_items.insert("Game".to_string(), vec![
+                SceneItem { name: "Capture".to_string(), visible: true },
+                SceneItem { name: "Score overlay".to_string(), visible: true },
+            ]);
+            state.audio.insert("Mic".to_string(), AudioState { muted: false, volume: 1.0 });
+        }
+        let server = spawn(Arc::clone(&backend));
+        let res: serde_json::Value = serde_json::from_str(&server.signed_get("/scenes/Game/sources").1).unwrap();
+        assert_eq!("Score overlay", res["sources"][1]["name"]);
+        assert_eq!(200, server.signed_put("/scenes/Game/sources/Score%20overlay/visible", r#"{"visible": false}"#).0);
+        assert!(!backend.state().scene_items["Game"][1].visible);
+        assert_eq!(400, server.signed_put("/scenes/Game/sources/Capture/visible", "false").0);
+        assert_eq!(200, server.signed_put("/sources/Mic/mute", r#"{"muted": true}"#).0);
+        assert_eq!(200, server.signed_put("/sources/Mic/volume", r#"{"multiplier": 0.5}"#).0);
+        let res: serde_json::Value = serde_json::from_str(&server.signed_get("/sources/Mic/audio").1).unwrap();
+        assert_eq!(true, res["muted"]);
+        assert_eq!(0.5, res["volume"]["multiplier"]);
+        // Sources without audio
+        assert_eq!(409, server.signed_put("/sources/Capture/mute", r#"{"muted": true}"#).0);
+        // Unknown names
+        let (status, body) = server.signed_put("/scenes/Game/sources/Camera/visible", r#"{"visible": false}"#);
+        assert_eq!(404, status);
+        assert_eq!(r#"{"error":"unknown_source","message":"No source with this name"}"#, body);
+        assert_eq!(404, server.signed_get("/scenes/Menu/sources").0);
+        assert_eq!(404, server.signed_get("/sources/Camera/audio").0);
+        assert_eq!(404, server.signed_put("/sources/Camera/volume", r#"{"db": -6}"#).0);
+    }
+
+    #[test]
+    pub fn screenshot() {
+        let backend = Arc::new(MockBackend::new());
+        backend.state().scenes = vec!["Game".to_string()];
+        let server = spawn(Arc::clone(&backend));
+        let (status, body) = server.signed_get("/screenshot?source=Game&width=64&height=36&format=base64");
+        assert_eq!(200, status);
+        let res: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!((64, 36), (res["width"].as_u64().unwrap(), res["height"].as_u64().unwrap()));
+        let png = base64::decode(res["image"].as_str().unwrap()).unwrap();
+        assert_eq!(b"\x89PNG\r\n\x1a\n", &png[..8]);
+
+        let mut stream = TcpStream::connect(("127.0.0.1", server.port)).unwrap();
+        write!(stream, "GET /screenshot HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n{}\r\n",
+               server.signed_lines("GET", "/screenshot")).unwrap();
+        let mut res = Vec::new();
+        stream.read_to_end(&mut res).unwrap();
+        let res = String::from_utf8_lossy(&res);
+        assert!(res.starts_with("HTTP/1.1 200"));
+        assert!(res.contains("Content-Type: image/png\r\n"));
+        assert!(res.contains("\r\n\r\n\u{fffd}PNG"));
+
+        assert_eq!(404, server.signed_get("/screenshot?source=Menu").0);
+        assert_eq!(400, server.signed_get("/screenshot?width=64").0);
+        assert_eq!(400, server.signed_get("/screenshot?width=0&height=36").0);
+        assert_eq!(400, server.signed_get("/screenshot?format=jpeg").0);
+    }
+
+    #[test]
+    pub fn scene_collections() {
+        let backend = Arc::new(MockBackend::new());
+        backend.state().collections = vec!["Tournament".to_string(), "Casual".to_string()];
+        let server = spawn(Arc::clone(&backend));
+        assert_eq!(200, server.signed_put("/scene-collections/current", r#"{"name": "Casual"}"#).0);
+        let res: serde_json::Value = serde_json::from_str(&server.signed_get("/scene-collections").1).unwrap();
+        assert_eq!(serde_json::json!(["Tournament", "Casual"]), res["collections"]);
+        assert_eq!("Casual", res["current"]);
+        assert_eq!(404, server.signed_put("/scene-collections/current", r#"{"name": "Speedrun"}"#).0);
+        assert_eq!(400, server.signed_put("/scene-collections/current", "Speedrun").0);
+    }
+
+    #[test]
+    pub fn replay() {
+        let backend = Arc::new(MockBackend::new());
+        let server = spawn(Arc::clone(&backend));
+        assert_eq!(409, server.signed("/replay/save", "").0);
+        assert_eq!(409, server.signed("/replay/stop", "").0);
+        assert_eq!(200, server.signed("/replay/start", "").0);
+        assert!(server.signed("/replay/start", "").1.contains("replay_buffer_active"));
+        let controller = Arc::clone(&server.controller);
+        backend.state().on_replay_saved = Some(Arc::new(move || controller.handle_event(EventKind::ReplayBufferSaved)));
+        assert_eq!(400, server.signed("/replay/save", "Goal %hh-%mm").0);
+        let (status, body) = server.signed("/replay/save", r#"{"name": "Goal %hh-%mm"}"#);
+        assert_eq!(200, status);
+        assert_eq!(r#"{"path":"/replays/Goal %hh-%mm.mkv"}"#, body);
+        assert_eq!(Some("Replay %CCYY-%MM-%DD %hh-%mm-%ss".to_string()), backend.replay_buffer_setting("format"));
+        assert_eq!(200, server.signed("/replay/stop", "").0);
+        assert!(!backend.state().replay_active);
+        assert!(server.signed("/replay/stop", "").1.contains("replay_buffer_inactive"));
+    }
+
+    #[test]
+    pub fn markers() {
+        let backend = Arc::new(MockBackend::new());
+        let dir = std::env::temp_dir().join(format!("obs-controller-markers-{:x}", OsRng.next_u64()));
+        std::fs::create_dir(&dir).unwrap();
+        let server = spawn(Arc::clone(&backend));
+        server.controller.recorder.set_ffmpeg_chapters(true);
+        assert_eq!(409, server.signed("/recording/marker", r#"{"label": "Kill"}"#).0);
+        assert_eq!(200, server.signed("/recording/start", "").0);
+        backend.state().output_settings.insert("path".to_string(), dir.join("Match.mkv").to_string_lossy().into_owned());
+        server.controller.handle_event(EventKind::RecordingStarted);
+        assert!(server.signed("/recording/marker", "Round 1").1.contains("invalid_json"));
+        assert!(server.signed("/recording/marker", r#"{"label": ""}"#).1.contains("invalid_label"));
+        let (status, body) = server.signed("/recording/marker", r#"{"label": "Round 1"}"#);
+        assert_eq!(200, status);
+        assert!(body.starts_with(r#"{"label":"Round 1","offset":"#));
+        // Paused time isn't counted
+        assert_eq!(200, server.signed("/recording/pause", "").0);
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(200, server.signed("/recording/resume", "").0);
+        assert_eq!(200, server.signed("/recording/marker", r#"{"label": "Kill"}"#).0);
+        let res: serde_json::Value = serde_json::from_str(&server.signed("/recording/stop", "").1).unwrap();
+        assert_eq!("Round 1", res["markers"][0]["label"]);
+        assert!(res["markers"][1]["offset"].as_u64().unwrap() < 200);
+        server.controller.handle_event(EventKind::RecordingStopped);
+        let sidecar: serde_json::Value = serde_json::from_slice(&std::fs::read(dir.join("Match.markers.json")).unwrap()).unwrap();
+        assert_eq!(res["markers"], sidecar);
+        assert!(std::fs::read_to_string(dir.join("Match.ffmetadata")).unwrap().contains("title=Kill\n"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    pub fn unauthenticated() {
+        let backend = Arc::new(MockBackend::new());
+        let server = spawn(Arc::clone(&backend));
+        assert_eq!(400, request(server.port, "/recording/start", &[], "").0);
+        let headers = [("X-OBSC-App", APP.to_string()), ("X-OBSC-Signature", base64::encode([0u8; 64]))];
+        assert_eq!(401, request(server.port, "/recording/start", &headers, "").0);
+        assert!(!backend.state().recording);
+    }
+
+    #[test]
+    pub fn replay_protection() {
+        let backend = Arc::new(MockBackend::new());
+        let server = spawn(Arc::clone(&backend));
+        let now = verification::unix_time();
+        let headers = server.signed_headers("POST", "/recording/stop", None, "", now, "0123456789abcdef");
+        assert_eq!(200, request(server.port, "/recording/stop", &headers, "").0);
+        let (status, body) = request(server.port, "/recording/stop", &headers, "");
+        assert_eq!(401, status);
+        assert!(body.contains("replayed_request"));
+        // The signature covers the method, path and content type
+        let headers = server.signed_headers("POST", "/recording/stop", None, "", now, "fedcba9876543210");
+        assert_eq!(401, request(server.port, "/recording/start", &headers, "").0);
+        let mut headers = server.signed_headers("POST", "/recording/start", Some("text/plain"), "Match", now, "fedcba9876543210");
+        headers.retain(|(name, _)| *name != "Content-Type");
+        headers.push(("Content-Type", "application/json".to_string()));
+        assert!(request(server.port, "/recording/start", &headers, "Match").1.contains("not_authenticated"));
+        assert!(!backend.state().recording);
+        let headers = server.signed_headers("POST", "/recording/start", None, "", now - 3600, "fedcba9876543210");
+        assert!(request(server.port, "/recording/start", &headers, "").1.contains("clock_skew"));
+        let headers = server.signed_headers("POST", "/recording/start", None, "", now, "short");
+        assert!(request(server.port, "/recording/start", &headers, "").1.contains("invalid_nonce"));
+        // Legacy signatures are only accepted when turned on, for clients that don't sign with the current protocol yet
+        let legacy = [("X-OBSC-App", APP.to_string()), ("X-OBSC-Signature", server.signature(""))];
+        assert!(request(server.port, "/recording/start", &legacy, "").1.contains("legacy_signature"));
+        server.controller.apps.set_legacy_signatures(true);
+        assert_eq!(200, request(server.port, "/recording/start", &legacy, "").0);
+    }
+
+    #[test]
+    pub fn register() {
+        let server = spawn(Arc::new(MockBackend::new()));
+        let public_key = base64::encode([9u8; 32]);
+        let body = format!(r#"{{"uuid": "{}", "name": "Game", "public_key": "{}"}}"#, Uuid::nil(), public_key);
+        let (status, res) = request(server.port, "/register", &[], &body);
+        assert_eq!(200, status);
+        let res: RegistrationResponse = serde_json::from_str(&res).unwrap();
+        assert_eq!((1, None), (res.version, res.nonce));
+        assert_eq!(409, request(server.port, "/register", &[], &body).0);
+        let body = format!(r#"{{"version": 2, "uuid": "{}", "name": "Game", "public_key": "{}"}}"#, Uuid::from_u128(1), public_key);
+        let res: RegistrationResponse = serde_json::from_str(&request(server.port, "/register", &[], &body).1).unwrap();
+        assert_eq!(2, res.version);
+        assert_eq!(12, base64::decode(res.nonce.unwrap()).unwrap().len());
+        let body = format!(r#"{{"version": 3, "uuid": "{}", "name": "Game", "public_key": "{}"}}"#, Uuid::nil(), public_key);
+        assert!(request(server.port, "/register", &[], &body).1.contains("unsupported_version"));
+        let body = format!(r#"{{"uuid": "{}", "name": "Denied", "public_key": "{}"}}"#, APP, public_key);
+        assert_eq!(401, request(server.port, "/register", &[], &body).0);
+        assert_eq!(400, request(server.port, "/register", &[], "Game").0);
+        let body = format!(r#"{{"uuid": "{}", "name": "Ga\u0000me", "public_key": "{}"}}"#, Uuid::nil(), public_key);
+        let (status, body) = request(server.port, "/register", &[], &body);
+        assert_eq!(400, status);
+        assert_eq!(r#"{"error": "invalid_name", "message": "Name must not contain NUL characters"}"#, body);
+    }
+
+    #[test]
+    pub fn apps() {
+        let server = spawn(Arc::new(MockBackend::new()));
+        let (status, body) = server.signed_get("/apps");
+        assert_eq!(403, status);
+        assert!(body.contains("forbidden"));
+        let config = Config { admin_apps: vec![APP.to_string()], ..Config::default() };
+        let server = spawn_with(Arc::new(MockBackend::new()), Box::new(|_: &str| true), config);
+        let registration = format!(r#"{{"uuid": "{}", "name": "Game", "public_key": "{}"}}"#, Uuid::nil(), base64::encode([9u8; 32]));
+        assert_eq!(200, request(server.port, "/register", &[], &registration).0);
+        let (status, body) = server.signed_get("/apps");
+        assert_eq!(200, status);
+        let res: AppsResponse = serde_json::from_str(&body).unwrap();
+        assert_eq!(vec![(APP.to_string(), "Test"), (Uuid::nil().to_string(), "Game")],
+                   res.apps.iter().map(|app| (app.uuid.clone(), app.name.as_str())).collect::<Vec<_>>());
+        assert!(res.apps.iter().all(|app| app.registered.is_some()));
+        let path = format!("/apps/{}", Uuid::nil());
+        // Even when they are accepted elsewhere, legacy signatures could be replayed to revoke apps
+        server.controller.apps.set_legacy_signatures(true);
+        let legacy = [("X-OBSC-App", APP.to_string()), ("X-OBSC-Signature", server.signature(""))];
+        let (status, body) = send(server.port, "DELETE", &path, &legacy, "");
+        assert_eq!(401, status);
+        assert!(body.contains("legacy_signature"));
+        assert!(send(server.port, "GET", "/apps", &legacy, "").1.contains("legacy_signature"));
+        assert_eq!(200, server.send_signed("DELETE", &path, "").0);
+        assert_eq!(404, server.send_signed("DELETE", &path, "").0);
+        assert_eq!(400, server.send_signed("DELETE", "/apps/nothing", "").0);
+        // Revoked apps can register again
+        assert_eq!(200, request(server.port, "/register", &[], &registration).0);
+        assert_eq!(200, server.send_signed("DELETE", &format!("/apps/{}", APP), "").0);
+        assert!(server.signed_get("/apps").1.contains("unknown_app"));
+    }
+
+    #[test]
+    pub fn errors() {
+        let backend = Arc::new(MockBackend::new());
+        let server = spawn(Arc::clone(&backend));
+        let (status, body) = request(server.port, "/recording/start", &[], "");
+        assert_eq!(400, status);
+        let res: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!("missing_credentials", res["error"]);
+        let (status, body) = server.signed_json("/recording/start", r#"{"name": "Match\u0000"}"#);
+        assert_eq!(400, status);
+        assert_eq!(r#"{"error":"invalid_name","message":"The file name formatting must not contain NUL characters"}"#, body);
+        assert!(!backend.state().recording);
+        let (status, body) = send(server.port, "GET", "/nothing", &[], "");
+        assert_eq!(404, status);
+        assert!(body.contains(r#""error": "not_found""#));
+    }
+
+    #[test]
+    pub fn pending_registration() {
+        let (tx, rx) = mpsc::channel();
+        let rx = Mutex::new(rx);
+        let backend = Arc::new(MockBackend::new());
+        let server = spawn_with(Arc::clone(&backend), Box::new(move |_: &str| rx.lock().unwrap().recv().unwrap()), Config::default());
+        let port = server.port;
+        let body = format!(r#"{{"uuid": "{}", "name": "Game", "public_key": "{}"}}"#, Uuid::nil(), base64::encode([9u8; 32]));
+        let registration = {
+            let body = body.clone();
+            thread::spawn(move || request(port, "/register", &[], &body).0)
+        };
+        thread::sleep(Duration::from_millis(100));
+        // The dialog is still open, but other requests are handled
+        assert_eq!(429, request(port, "/register", &[], &body).0);
+        assert_eq!(200, server.signed("/recording/start", "").0);
+        tx.send(true).unwrap();
+        assert_eq!(200, registration.join().unwrap());
+    }
+
+    #[test]
+    pub fn events() {
+        let backend = Arc::new(MockBackend::new());
+        let server = spawn(Arc::clone(&backend));
+        let mut stream = TcpStream::connect(("127.0.0.1", server.port)).unwrap();
+        write!(stream, "GET /events HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                        Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n{}\r\n",
+               server.signed_lines("GET", "/events")).unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut headers = Vec::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            headers.push(line);
+        }
+        assert!(headers[0].starts_with("HTTP/1.1 101"));
+        assert!(headers.contains(&"Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n".to_string()));
+
+        backend.state().scene = Some("Game".to_string());
+        server.controller.handle_event(EventKind::SceneChanged);
+        let mut frame = [0u8; 2];
+        reader.read_exact(&mut frame).unwrap();
+        assert_eq!(0x81, frame[0]);
+        let mut payload = vec![0u8; frame[1] as usize];
+        reader.read_exact(&mut payload).unwrap();
+        let event: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+        assert_eq!("scene_changed", event["type"]);
+        assert_eq!("Game", event["scene"]);
+
+        server.controller.events.close();
+        let mut close = [0u8; 4];
+        reader.read_exact(&mut close).unwrap();
+        assert_eq!([0x88, 2, 0x03, 0xe9], close);
+    }
+
+    #[test]
+    pub fn events_sse() {
+        let server = spawn(Arc::new(MockBackend::new()));
+        server.controller.handle_event(EventKind::StreamingStarted);
+        server.controller.handle_event(EventKind::StreamingStopped);
+        let mut stream = TcpStream::connect(("127.0.0.1", server.port)).unwrap();
+        write!(stream, "GET /events/sse HTTP/1.1\r\nHost: localhost\r\nLast-Event-ID: 1\r\n{}\r\n",
+               server.signed_lines("GET", "/events/sse")).unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!("HTTP/1.1 200 OK\r\n", line);
+        while line != "\r\n" {
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+        }
+        let mut next_chunk = || {
+            let mut size = String::new();
+            reader.read_line(&mut size).unwrap();
+            let mut chunk = vec![0u8; usize::from_str_radix(size.trim_end(), 16).unwrap() + 2];
+            reader.read_exact(&mut chunk).unwrap();
+            String::from_utf8(chunk).unwrap()
+        };
+        // Missed event first, then live ones
+        assert!(next_chunk().starts_with("id: 2\nevent: streaming_stopped\n"));
+        server.controller.handle_event(EventKind::RecordingPaused);
+        assert!(next_chunk().starts_with("id: 3\nevent: recording_paused\n"));
+        server.controller.events.close();
+        assert_eq!("\r\n", next_chunk());
+    }
+
+    #[test]
+    pub fn events_handshake() {
+        let server = spawn(Arc::new(MockBackend::new()));
+        let mut stream = TcpStream::connect(("127.0.0.1", server.port)).unwrap();
+        write!(stream, "GET /events HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").unwrap();
+        let mut res = String::new();
+        stream.read_to_string(&mut res).unwrap();
+        assert!(res.starts_with("HTTP/1.1 426"));
+        let mut stream = TcpStream::connect(("127.0.0.1", server.port)).unwrap();
+        write!(stream, "GET /events HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                        Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n").unwrap();
+        let mut res = [0u8; 12];
+        stream.read_exact(&mut res).unwrap();
+        assert_eq!(b"HTTP/1.1 400", &res);
+        let mut stream = TcpStream::connect(("127.0.0.1", server.port)).unwrap();
+        write!(stream, "GET /events/sse HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").unwrap();
+        let mut res = String::new();
+        stream.read_to_string(&mut res).unwrap();
+        assert!(res.starts_with("HTTP/1.1 400"));
+    }
+
+    #[test]
+    pub fn not_found() {
+        let server = spawn(Arc::new(MockBackend::new()));
+        assert_eq!(404, request(server.port, "/recording/unknown", &[], "").0);
+    }
+}
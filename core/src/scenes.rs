@@ -0,0 +1,144 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Scene and scene collection switching.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::backend::ObsBackend;
+use crate::types::{CollectionsResponse, ScenesResponse};
+
+/// Why a scene or scene collection couldn't be switched to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SceneError {
+    EmptyName,
+    UnknownScene,
+    UnknownCollection,
+    /// The preview scene only exists in studio mode.
+    StudioModeDisabled
+}
+
+impl SceneError {
+    /// The HTTP status reported for this error.
+    pub fn status(self) -> u16 {
+        match self {
+            SceneError::EmptyName => 400,
+            SceneError::UnknownScene | SceneError::UnknownCollection => 404,
+            SceneError::StudioModeDisabled => 409
+        }
+    }
+
+    /// The error code reported for this error.
+    pub fn code(self) -> &'static str {
+        match self {
+            SceneError::EmptyName => "empty_name",
+            SceneError::UnknownScene => "unknown_scene",
+            SceneError::UnknownCollection => "unknown_collection",
+            SceneError::StudioModeDisabled => "studio_mode_disabled"
+        }
+    }
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SceneError::EmptyName => "Body cannot be empty",
+            SceneError::UnknownScene => "No scene with this name",
+            SceneError::UnknownCollection => "No scene collection with this name",
+            SceneError::StudioModeDisabled => "Studio mode is not enabled"
+        })
+    }
+}
+
+pub struct Scenes {
+    backend: Arc<dyn ObsBackend>
+}
+
+impl Scenes {
+    pub fn new(backend: Arc<dyn ObsBackend>) -> Scenes {
+        Scenes { backend }
+    }
+
+    /// Lists the scenes of the current collection, with the program and preview scenes.
+    pub fn list(&self) -> ScenesResponse {
+        let studio_mode = self.backend.studio_mode_active();
+        ScenesResponse {
+            scenes: self.backend.scene_names(),
+            current: self.backend.current_scene(),
+            preview: if studio_mode { self.backend.preview_scene() } else { None },
+            studio_mode
+        }
+    }
+
+    /// The program scene, which is also the only scene outside of studio mode.
+    pub fn current(&self) -> Option<String> {
+        self.backend.current_scene()
+    }
+
+    /// Switches the program scene. In studio mode, the preview scene is left untouched.
+    pub fn set_current(&self, name: &str) -> Result<(), SceneError> {
+        self.check_scene(name)?;
+        self.backend.set_current_scene(name);
+        Ok(())
+    }
+
+    pub fn preview(&self) -> Result<Option<String>, SceneError> {
+        if !self.backend.studio_mode_active() {
+            return Err(SceneError::StudioModeDisabled);
+        }
+        Ok(self.backend.preview_scene())
+    }
+
+    pub fn set_preview(&self, name: &str) -> Result<(), SceneError> {
+        self.check_scene(name)?;
+        if !self.backend.studio_mode_active() {
+            return Err(SceneError::StudioModeDisabled);
+        }
+        self.backend.set_preview_scene(name);
+        Ok(())
+    }
+
+    pub fn collections(&self) -> CollectionsResponse {
+        CollectionsResponse {
+            collections: self.backend.scene_collections(),
+            current: self.backend.current_scene_collection()
+        }
+    }
+
+    pub fn set_collection(&self, name: &str) -> Result<(), SceneError> {
+        if name.is_empty() {
+            return Err(SceneError::EmptyName);
+        }
+        if !self.backend.scene_collections().iter().any(|collection| collection == name) {
+            return Err(SceneError::UnknownCollection);
+        }
+        self.backend.set_scene_collection(name);
+        Ok(())
+    }
+
+    fn check_scene(&self, name: &str) -> Result<(), SceneError> {
+        if name.is_empty() {
+            return Err(SceneError::EmptyName);
+        }
+        if !self.backend.scene_names().iter().any(|scene| scene == name) {
+            return Err(SceneError::UnknownScene);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Still frames of the program output or of a source.
+
+use std::fmt;
+
+/// The largest width or height that can be requested.
+pub const MAX_SIZE: u32 = 4096;
+
+/// A rendered frame.
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    /// RGBA pixels, row by row
+    pub data: Vec<u8>
+}
+
+/// Why a frame couldn't be rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureError {
+    UnknownSource,
+    /// The frame provider can't render frames, e.g. because there's no graphics context.
+    Unsupported,
+    Failed
+}
+
+impl CaptureError {
+    /// The HTTP status reported for this error.
+    pub fn status(self) -> u16 {
+        match self {
+            CaptureError::UnknownSource => 404,
+            CaptureError::Unsupported => 501,
+            CaptureError::Failed => 500
+        }
+    }
+
+    /// The error code reported for this error.
+    pub fn code(self) -> &'static str {
+        match self {
+            CaptureError::UnknownSource => "unknown_source",
+            CaptureError::Unsupported => "screenshots_unsupported",
+            CaptureError::Failed => "capture_failed"
+        }
+    }
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CaptureError::UnknownSource => "No source with this name",
+            CaptureError::Unsupported => "Screenshots are not supported",
+            CaptureError::Failed => "The frame couldn't be rendered"
+        })
+    }
+}
+
+/// Renders frames for `/screenshot`.
+///
+/// The plugin renders with the OBS graphics subsystem, tests can use [`MockBackend`](crate::mock::MockBackend).
+pub trait FrameProvider: Send + Sync {
+    /// Renders the program output, or the source with this name, scaled to `size` if set.
+    fn capture(&self, source: Option<&str>, size: Option<(u32, u32)>) -> Result<Frame, CaptureError>;
+}
+
+/// Frame provider of controllers that weren't given one.
+pub struct NoFrames;
+
+impl FrameProvider for NoFrames {
+    fn capture(&self, _source: Option<&str>, _size: Option<(u32, u32)>) -> Result<Frame, CaptureError> {
+        Err(CaptureError::Unsupported)
+    }
+}
+
+/// Encodes a frame as an 8-bit RGBA PNG.
+pub fn encode_png(frame: &Frame) -> Result<Vec<u8>, png::EncodingError> {
+    let mut buf = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut buf, frame.width, frame.height);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&frame.data)?;
+    }
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_png, Frame};
+
+    #[test]
+    pub fn png() {
+        let frame = Frame { width: 2, height: 1, data: vec![255, 0, 0, 255, 0, 0, 255, 128] };
+        let png = encode_png(&frame).unwrap();
+        assert_eq!(b"\x89PNG\r\n\x1a\n", &png[..8]);
+        let (info, mut reader) = png::Decoder::new(&png[..]).read_info().unwrap();
+        assert_eq!((2, 1), (info.width, info.height));
+        let mut data = vec![0; info.buffer_size()];
+        reader.next_frame(&mut data).unwrap();
+        assert_eq!(frame.data, data);
+    }
+}
@@ -0,0 +1,392 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::error::Error;
+use std::io::{self, Read};
+use std::net::SocketAddr;
+use std::ops::{Deref, DerefMut};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tiny_http::{Request, Response, Server, StatusCode, Header, Method};
+
+use crate::config::Config;
+use crate::router::{Match, ResCallback, Router};
+
+/// How often the server threads check for a shutdown request.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct HttpServer {
+    config: Config,
+    router: Router,
+    shutdown: ShutdownHandle
+}
+
+impl HttpServer {
+    pub fn new(config: Config) -> HttpServer {
+        HttpServer { config, router: Router::default(), shutdown: ShutdownHandle::default() }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Returns a handle that stops the server from another thread.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    pub fn add_route(&mut self, method: Method, path: &str, callback: ResCallback) {
+        self.router.add(method, path, callback);
+    }
+
+    pub fn run(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.serve_all(self.bind()?)
+    }
+
+    /// Binds the configured addresses.
+    /// Ports in use are logged and skipped, this only fails if no address could be bound.
+    pub fn bind(&self) -> Result<Vec<Server>, Box<dyn Error + Send + Sync>> {
+        let mut listeners = Vec::new();
+        let attempts = self.config.port_attempts.max(1);
+        for port in (self.config.port..=u16::MAX).take(attempts as usize) {
+            let addr = SocketAddr::new(self.config.bind_address, port);
+            match Server::http(addr) {
+                Ok(server) => {
+                    log::info!("Listening on {}", addr);
+                    listeners.push(server);
+                    break;
+                }
+                Err(e) => log::warn!("Couldn't listen on {}: {}", addr, e)
+            }
+        }
+        for &addr in &self.config.extra_addresses {
+            match Server::http(addr) {
+                Ok(server) => {
+                    log::info!("Listening on {}", addr);
+                    listeners.push(server);
+                }
+                Err(e) => log::warn!("Couldn't listen on {}: {}", addr, e)
+            }
+        }
+        if listeners.is_empty() {
+            return Err("Couldn't listen on any address".into());
+        }
+        Ok(listeners)
+    }
+
+    /// Handles requests coming from an already bound server.
+    pub fn serve(&self, server: Server) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.serve_all(vec![server])
+    }
+
+    /// Handles requests coming from several bound servers, until a shutdown is requested.
+    /// Requests are dispatched to a pool of `workers` threads.
+    pub fn serve_all(&self, servers: Vec<Server>) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let _stopped = StoppedGuard(&self.shutdown);
+        let (tx, rx) = mpsc::channel::<Request>();
+        let rx = Mutex::new(rx);
+        thread::scope(|scope| {
+            for server in servers {
+                let tx = tx.clone();
+                // The server is dropped when this thread ends, which frees the port
+                scope.spawn(move || {
+                    while !self.shutdown.is_requested() {
+                        match server.recv_timeout(POLL_INTERVAL) {
+                            Ok(Some(request)) => if tx.send(request).is_err() { break },
+                            Ok(None) => {}
+                            Err(_) => break
+                        }
+                    }
+                });
+            }
+            // Workers stop once all the listeners have stopped and the queue is empty
+            drop(tx);
+            for _ in 0..self.config.workers.max(1) {
+                scope.spawn(|| loop {
+                    let request = match rx.lock().unwrap_or_else(PoisonError::into_inner).recv() {
+                        Ok(request) => request,
+                        Err(_) => break
+                    };
+                    let res = if self.shutdown.before_deadline() {
+                        // A panicking route mustn't take the worker down with it.
+                        // Its responder is dropped while unwinding, which answers with an internal error.
+                        panic::catch_unwind(AssertUnwindSafe(|| self.handle(request))).unwrap_or_else(|_| {
+                            log::error!("A route panicked while handling a request");
+                            Ok(())
+                        })
+                    } else {
+                        request.respond(json_response(503, r#"{"error": "shutting_down", "message": "The server is shutting down"}"#))
+                    };
+                    if let Err(e) = res {
+                        log::warn!("Couldn't respond to request: {}", e);
+                    }
+                });
+            }
+        });
+        Ok(())
+    }
+
+    fn handle(&self, request: Request) -> io::Result<()> {
+        match self.router.find(request.method(), request.url()) {
+            Match::Found(route, params) => route(Responder(Some(request)), params),
+            Match::MethodNotAllowed(allowed) => {
+                let allowed = allowed.iter().map(|m| m.to_string()).collect::<Vec<_>>().join(", ");
+                let allow = Header::from_bytes(&b"Allow"[..], allowed.as_bytes()).unwrap();
+                let res = json_response(405, r#"{"error": "method_not_allowed", "message": "Method not allowed"}"#);
+                request.respond(res.with_header(allow))
+            }
+            Match::NotFound => request.respond(json_response(404, r#"{"error": "not_found", "message": "No such route"}"#))
+        }
+    }
+}
+
+/// A request that its route must respond to.
+/// If the route panics or returns without responding, it is answered with a JSON internal error,
+/// instead of the empty 500 tiny_http sends for dropped requests.
+pub struct Responder(Option<Request>);
+
+impl Responder {
+    pub fn respond<R: Read>(mut self, res: Response<R>) -> io::Result<()> {
+        self.0.take().expect("Request already taken").respond(res)
+    }
+
+    /// Takes the request out, e.g. to upgrade its connection. Responding to it is then up to the caller.
+    pub fn into_request(mut self) -> Request {
+        self.0.take().expect("Request already taken")
+    }
+}
+
+impl Deref for Responder {
+    type Target = Request;
+
+    fn deref(&self) -> &Request {
+        self.0.as_ref().expect("Request already taken")
+    }
+}
+
+impl DerefMut for Responder {
+    fn deref_mut(&mut self) -> &mut Request {
+        self.0.as_mut().expect("Request already taken")
+    }
+}
+
+impl Drop for Responder {
+    fn drop(&mut self) {
+        if let Some(request) = self.0.take() {
+            let res = json_response(500, r#"{"error": "internal_error", "message": "The request couldn't be handled"}"#);
+            if let Err(e) = request.respond(res) {
+                log::warn!("Couldn't respond to request: {}", e);
+            }
+        }
+    }
+}
+
+/// Stops an [`HttpServer`] and waits for it to finish the requests it has accepted.
+#[derive(Clone, Default)]
+pub struct ShutdownHandle {
+    state: Arc<ShutdownState>
+}
+
+#[derive(Default)]
+struct ShutdownState {
+    requested: AtomicBool,
+    deadline: Mutex<Option<Instant>>,
+    stopped: Mutex<bool>,
+    cond: Condvar
+}
+
+impl ShutdownHandle {
+    /// Stops accepting requests and waits up to `timeout` for the in-flight ones.
+    /// Returns `false` if the server is still running after the timeout.
+    pub fn shutdown(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        *self.state.deadline.lock().unwrap_or_else(PoisonError::into_inner) = Some(deadline);
+        self.state.requested.store(true, Ordering::SeqCst);
+        let stopped = self.state.stopped.lock().unwrap_or_else(PoisonError::into_inner);
+        let (stopped, _) = self.state.cond.wait_timeout_while(stopped, timeout, |stopped| !*stopped)
+            .unwrap_or_else(PoisonError::into_inner);
+        *stopped
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.state.requested.load(Ordering::SeqCst)
+    }
+
+    fn before_deadline(&self) -> bool {
+        self.state.deadline.lock().unwrap_or_else(PoisonError::into_inner).map_or(true, |deadline| Instant::now() < deadline)
+    }
+}
+
+/// Notifies the [`ShutdownHandle`] when the server stops, even if it stops because of an error.
+struct StoppedGuard<'a>(&'a ShutdownHandle);
+
+impl Drop for StoppedGuard<'_> {
+    fn drop(&mut self) {
+        let mut stopped = match self.0.state.stopped.lock() {
+            Ok(stopped) => stopped,
+            Err(poisoned) => poisoned.into_inner()
+        };
+        *stopped = true;
+        self.0.state.cond.notify_all();
+    }
+}
+
+pub fn json_response(status: u16, text: &str) -> Response<&[u8]> {
+    Response::new(StatusCode(status),
+                  vec![Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap()],
+                  text.as_bytes(), Some(text.len()), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex, PoisonError};
+    use std::thread;
+    use std::time::Duration;
+
+    use tiny_http::{Method, Response, Server};
+
+    use crate::config::Config;
+
+    use super::HttpServer;
+
+    /// Serves a route that takes `delay` to respond, returning the server address and its shutdown handle.
+    fn spawn_slow(delay: Duration) -> (SocketAddr, super::ShutdownHandle, thread::JoinHandle<()>) {
+        let mut server = HttpServer::new(Config::default());
+        server.add_route(Method::Get, "/slow", Box::new(move |req, _| {
+            thread::sleep(delay);
+            req.respond(Response::from_string("done"))
+        }));
+        let listener = Server::http("127.0.0.1:0").unwrap();
+        let addr = listener.server_addr();
+        let shutdown = server.shutdown_handle();
+        let thread = thread::spawn(move || server.serve(listener).unwrap());
+        (addr, shutdown, thread)
+    }
+
+    fn get(addr: SocketAddr, path: &str) -> thread::JoinHandle<String> {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path).unwrap();
+        thread::spawn(move || {
+            let mut res = String::new();
+            stream.read_to_string(&mut res).ok();
+            res
+        })
+    }
+
+    #[test]
+    pub fn method_not_allowed() {
+        let (addr, _, _) = spawn_slow(Duration::from_millis(0));
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "DELETE /slow HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").unwrap();
+        let mut res = String::new();
+        stream.read_to_string(&mut res).unwrap();
+        assert!(res.starts_with("HTTP/1.1 405"));
+        assert!(res.contains("Allow: GET\r\n"));
+        assert!(res.ends_with(r#"{"error": "method_not_allowed", "message": "Method not allowed"}"#));
+    }
+
+    #[test]
+    pub fn panic_guard() {
+        let mut server = HttpServer::new(Config { workers: 1, ..Config::default() });
+        let state = Arc::new(Mutex::new(()));
+        let panic_state = state.clone();
+        server.add_route(Method::Get, "/panic", Box::new(move |_, _| {
+            let _state = panic_state.lock().unwrap_or_else(PoisonError::into_inner);
+            panic!("Bad request")
+        }));
+        server.add_route(Method::Get, "/ok", Box::new(move |req, _| {
+            let _state = state.lock().unwrap_or_else(PoisonError::into_inner);
+            req.respond(Response::from_string("done"))
+        }));
+        let listener = Server::http("127.0.0.1:0").unwrap();
+        let addr = listener.server_addr();
+        thread::spawn(move || server.serve(listener).unwrap());
+        let res = get(addr, "/panic").join().unwrap();
+        assert!(res.starts_with("HTTP/1.1 500"));
+        assert!(res.contains("Content-Type: application/json"));
+        assert!(res.contains(r#""error": "internal_error""#));
+        // The only worker is still alive, and the lock poisoned by the panic is still usable
+        assert!(get(addr, "/ok").join().unwrap().ends_with("done"));
+    }
+
+    #[test]
+    pub fn concurrent() {
+        let (addr, _, _) = spawn_slow(Duration::from_millis(500));
+        let start = std::time::Instant::now();
+        let requests: Vec<_> = (0..3).map(|_| get(addr, "/slow")).collect();
+        for res in requests {
+            assert!(res.join().unwrap().ends_with("done"));
+        }
+        assert!(start.elapsed() < Duration::from_millis(1200));
+    }
+
+    #[test]
+    pub fn shutdown() {
+        let (addr, shutdown, thread) = spawn_slow(Duration::from_millis(300));
+        let res = get(addr, "/slow");
+        thread::sleep(Duration::from_millis(100));
+        assert!(shutdown.shutdown(Duration::from_secs(5)));
+        thread.join().unwrap();
+        // The in-flight request was completed, and the port was released
+        assert!(res.join().unwrap().ends_with("done"));
+        TcpListener::bind(addr).unwrap();
+    }
+
+    #[test]
+    pub fn shutdown_timeout() {
+        let (addr, shutdown, _) = spawn_slow(Duration::from_secs(2));
+        get(addr, "/slow");
+        thread::sleep(Duration::from_millis(100));
+        assert!(!shutdown.shutdown(Duration::from_millis(200)));
+    }
+
+    #[test]
+    pub fn port_in_use() {
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        let config = Config { port: taken.local_addr().unwrap().port(), port_attempts: 5, ..Config::default() };
+        let listeners = HttpServer::new(config).bind().unwrap();
+        assert_eq!(1, listeners.len());
+        assert_ne!(taken.local_addr().unwrap(), listeners[0].server_addr());
+    }
+
+    #[test]
+    pub fn no_address() {
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        let config = Config { port: taken.local_addr().unwrap().port(), port_attempts: 1, ..Config::default() };
+        assert!(HttpServer::new(config).bind().is_err());
+    }
+
+    #[test]
+    pub fn extra_addresses() {
+        let config = Config {
+            port: 0,
+            extra_addresses: vec![SocketAddr::from(([127, 0, 0, 1], 0)), SocketAddr::from((Ipv6Addr::LOCALHOST, 0))],
+            ..Config::default()
+        };
+        // The IPv6 loopback may be unavailable, which is logged and skipped
+        let listeners = HttpServer::new(config).bind().unwrap();
+        assert!(listeners.len() >= 2);
+    }
+}
@@ -0,0 +1,160 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Scene item visibility and audio source control.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::backend::{AudioState, ObsBackend};
+use crate::types::{AudioResponse, SceneItem, Volume};
+
+/// The highest volume that can be set, as a multiplier (about +26 dB).
+const MAX_VOLUME: f32 = 20.0;
+
+/// Why a source couldn't be read or changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceError {
+    UnknownScene,
+    /// No source with this name, or it isn't in the scene.
+    UnknownSource,
+    /// The source has no audio.
+    NotAudio,
+    InvalidVolume
+}
+
+impl SourceError {
+    /// The HTTP status reported for this error.
+    pub fn status(self) -> u16 {
+        match self {
+            SourceError::UnknownScene | SourceError::UnknownSource => 404,
+            SourceError::NotAudio => 409,
+            SourceError::InvalidVolume => 400
+        }
+    }
+
+    /// The error code reported for this error.
+    pub fn code(self) -> &'static str {
+        match self {
+            SourceError::UnknownScene => "unknown_scene",
+            SourceError::UnknownSource => "unknown_source",
+            SourceError::NotAudio => "not_audio",
+            SourceError::InvalidVolume => "invalid_volume"
+        }
+    }
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SourceError::UnknownScene => "No scene with this name",
+            SourceError::UnknownSource => "No source with this name",
+            SourceError::NotAudio => "The source has no audio",
+            SourceError::InvalidVolume => "The volume must be either a multiplier within [0;20] or at most 26 dB"
+        })
+    }
+}
+
+pub struct Sources {
+    backend: Arc<dyn ObsBackend>
+}
+
+impl Sources {
+    pub fn new(backend: Arc<dyn ObsBackend>) -> Sources {
+        Sources { backend }
+    }
+
+    /// Lists the sources of a scene, in the order OBS shows them from the bottom.
+    pub fn list(&self, scene: &str) -> Result<Vec<SceneItem>, SourceError> {
+        self.backend.scene_items(scene).ok_or(SourceError::UnknownScene)
+    }
+
+    /// Shows or hides a source in a scene.
+    pub fn set_visible(&self, scene: &str, source: &str, visible: bool) -> Result<(), SourceError> {
+        let items = self.list(scene)?;
+        if !items.iter().any(|item| item.name == source) {
+            return Err(SourceError::UnknownSource);
+        }
+        self.backend.set_scene_item_visible(scene, source, visible);
+        Ok(())
+    }
+
+    pub fn audio(&self, source: &str) -> Result<AudioResponse, SourceError> {
+        let audio = self.audio_state(source)?;
+        Ok(AudioResponse { muted: audio.muted, volume: Volume::from_multiplier(audio.volume) })
+    }
+
+    pub fn set_muted(&self, source: &str, muted: bool) -> Result<(), SourceError> {
+        self.audio_state(source)?;
+        self.backend.set_source_muted(source, muted);
+        Ok(())
+    }
+
+    /// Sets the volume of a source, given either in dB or as a multiplier.
+    pub fn set_volume(&self, source: &str, volume: &Volume) -> Result<(), SourceError> {
+        let multiplier = match (volume.multiplier, volume.db) {
+            (Some(multiplier), None) => multiplier,
+            (None, Some(db)) => 10f32.powf(db / 20.0),
+            _ => return Err(SourceError::InvalidVolume)
+        };
+        if !(0.0..=MAX_VOLUME).contains(&multiplier) {
+            return Err(SourceError::InvalidVolume);
+        }
+        self.audio_state(source)?;
+        self.backend.set_source_volume(source, multiplier);
+        Ok(())
+    }
+
+    fn audio_state(&self, source: &str) -> Result<AudioState, SourceError> {
+        if !self.backend.source_exists(source) {
+            return Err(SourceError::UnknownSource);
+        }
+        self.backend.source_audio(source).ok_or(SourceError::NotAudio)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::backend::AudioState;
+    use crate::mock::MockBackend;
+    use crate::types::Volume;
+
+    use super::{SourceError, Sources};
+
+    #[test]
+    pub fn volume() {
+        let backend = Arc::new(MockBackend::new());
+        backend.state().audio.insert("Mic".to_string(), AudioState { muted: false, volume: 1.0 });
+        let sources = Sources::new(Arc::clone(&backend) as _);
+        assert_eq!(Some(0.0), sources.audio("Mic").unwrap().volume.db);
+        sources.set_volume("Mic", &Volume { db: Some(-20.0), multiplier: None }).unwrap();
+        assert!((backend.state().audio["Mic"].volume - 0.1).abs() < 1e-6);
+        sources.set_volume("Mic", &Volume { db: None, multiplier: Some(0.0) }).unwrap();
+        // Silence has no dB value
+        assert_eq!(None, sources.audio("Mic").unwrap().volume.db);
+        let invalid = [Volume { db: Some(30.0), multiplier: None }, Volume { db: None, multiplier: Some(-1.0) },
+            Volume { db: None, multiplier: Some(f32::NAN) }, Volume { db: Some(0.0), multiplier: Some(1.0) },
+            Volume { db: None, multiplier: None }];
+        for volume in &invalid {
+            assert_eq!(Err(SourceError::InvalidVolume), sources.set_volume("Mic", volume));
+        }
+        assert_eq!(Err(SourceError::UnknownSource), sources.set_volume("Camera", &Volume { db: Some(0.0), multiplier: None }));
+    }
+}
@@ -0,0 +1,95 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! The `/events/sse` Server-Sent Events endpoint.
+
+use std::io::{self, Write};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::events::Event;
+use crate::server::{json_response, Responder};
+use crate::verification::{self, VerificationResult};
+use crate::Controller;
+
+/// Comments are sent when there are no events, to notice disconnected clients.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Checks the request, then streams events to the client on a dedicated thread.
+/// Clients that send `Last-Event-ID` first get the remembered events they missed.
+pub fn serve_events(mut req: Responder, controller: Arc<Controller>) -> io::Result<()> {
+    if let VerificationResult::JsonReject(status, msg) = verification::middleware_auth(&mut req, &controller.apps) {
+        return req.respond(json_response(status, msg));
+    }
+    let last_id = req.headers().iter().find(|h| h.field.equiv("Last-Event-ID"))
+        .and_then(|h| h.value.as_str().trim().parse().ok());
+    let (missed, events) = controller.events.subscribe_after(last_id);
+    // The response is written by hand: tiny_http buffers chunked bodies, which would delay events.
+    let mut writer = req.into_request().into_writer();
+    thread::spawn(move || {
+        if let Err(e) = stream_events(&mut writer, missed, events) {
+            log::debug!("Event client disconnected: {}", e);
+        }
+    });
+    Ok(())
+}
+
+fn stream_events(writer: &mut dyn Write, missed: Vec<Arc<Event>>, events: Receiver<Arc<Event>>) -> io::Result<()> {
+    writer.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nTransfer-Encoding: chunked\r\n\r\n")?;
+    writer.flush()?;
+    for event in missed {
+        write_chunk(writer, &format_event(&event)?)?;
+    }
+    loop {
+        match events.recv_timeout(KEEPALIVE_INTERVAL) {
+            Ok(event) => write_chunk(writer, &format_event(&event)?)?,
+            Err(RecvTimeoutError::Timeout) => write_chunk(writer, ": keepalive\n\n")?,
+            // An empty chunk ends the response
+            Err(RecvTimeoutError::Disconnected) => return write_chunk(writer, "")
+        }
+    }
+}
+
+fn format_event(event: &Event) -> io::Result<String> {
+    let kind = serde_json::to_value(event.kind)?;
+    Ok(format!("id: {}\nevent: {}\ndata: {}\n\n", event.id, kind.as_str().unwrap_or_default(), serde_json::to_string(event)?))
+}
+
+fn write_chunk(writer: &mut dyn Write, data: &str) -> io::Result<()> {
+    write!(writer, "{:x}\r\n{}\r\n", data.len(), data)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_event;
+    use crate::events::{Event, EventKind};
+
+    #[test]
+    pub fn format() {
+        let mut event = Event::new(EventKind::RecordingStopped);
+        event.id = 3;
+        event.timestamp = 1600000000000;
+        event.path = Some("/recordings/Match.mkv".to_string());
+        assert_eq!("id: 3\nevent: recording_stopped\n\
+                    data: {\"id\":3,\"type\":\"recording_stopped\",\"timestamp\":1600000000000,\"path\":\"/recordings/Match.mkv\"}\n\n",
+                   format_event(&event).unwrap());
+    }
+}
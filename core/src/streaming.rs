@@ -0,0 +1,83 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Control of the OBS streaming output.
+
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Instant;
+
+use crate::backend::ObsBackend;
+use crate::types::StreamingStatus;
+
+pub struct Streamer {
+    backend: Arc<dyn ObsBackend>,
+    /// When the current stream started
+    started: Mutex<Option<Instant>>
+}
+
+impl Streamer {
+    pub fn new(backend: Arc<dyn ObsBackend>) -> Streamer {
+        Streamer { backend, started: Mutex::new(None) }
+    }
+
+    /// Starts streaming, returning false if the stream was already live.
+    pub fn start(&self) -> bool {
+        if self.backend.streaming_active() {
+            return false;
+        }
+        self.backend.streaming_start();
+        true
+    }
+
+    /// Stops streaming, returning false if there was no stream.
+    pub fn stop(&self) -> bool {
+        if !self.backend.streaming_active() {
+            return false;
+        }
+        self.backend.streaming_stop();
+        true
+    }
+
+    /// Reports the state of the streaming output, wherever it was started from.
+    pub fn status(&self) -> StreamingStatus {
+        if !self.backend.streaming_active() {
+            return StreamingStatus::default();
+        }
+        let started = *self.started.lock().unwrap_or_else(PoisonError::into_inner).get_or_insert_with(Instant::now);
+        let stats = self.backend.streaming_stats().unwrap_or_default();
+        StreamingStatus {
+            active: true,
+            reconnecting: self.backend.streaming_reconnecting(),
+            uptime: started.elapsed().as_millis() as u64,
+            congestion: self.backend.streaming_congestion(),
+            bytes: stats.bytes,
+            dropped_frames: stats.dropped_frames,
+            total_frames: stats.total_frames
+        }
+    }
+
+    /// Called when OBS reports the stream has started.
+    pub fn on_started(&self) {
+        *self.started.lock().unwrap_or_else(PoisonError::into_inner) = Some(Instant::now());
+    }
+
+    /// Called when OBS reports the stream has stopped.
+    pub fn on_stopped(&self) {
+        self.started.lock().unwrap_or_else(PoisonError::into_inner).take();
+    }
+}
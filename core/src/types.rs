@@ -0,0 +1,300 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Request and response bodies of the HTTP API.
+
+use serde::{Deserialize, Serialize};
+
+/// Body of `/register`.
+#[derive(Serialize, Deserialize)]
+pub struct AppRegistrationData {
+    /// The registration protocol, 1 (the legacy key wrapping) when missing,
+    /// or [`REGISTRATION_VERSION`](crate::verification::REGISTRATION_VERSION)
+    #[serde(default = "legacy_registration")]
+    pub version: u32,
+    pub uuid: String,
+    pub name: String,
+    /// The app's X25519 public key, in Base64
+    pub public_key: String,
+}
+
+/// Response of a successful `/register`.
+#[derive(Serialize, Deserialize)]
+pub struct RegistrationResponse {
+    /// The registration protocol used to encrypt `key`, 1 when missing
+    #[serde(default = "legacy_registration")]
+    pub version: u32,
+    /// The app's Ed25519 secret key, encrypted with the X25519 shared secret, in Base64
+    pub key: String,
+    /// The server's X25519 public key, in Base64
+    pub shared_public: String,
+    /// The AES-256-GCM nonce used to encrypt `key`, in Base64; missing (all zeroes) with the legacy protocol
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+}
+
+fn legacy_registration() -> u32 {
+    1
+}
+
+/// An app of `GET /apps`.
+#[derive(Serialize, Deserialize)]
+pub struct RegisteredApp {
+    pub uuid: String,
+    pub name: String,
+    /// When the app was registered, in seconds since the Unix epoch; missing for apps registered before dates were saved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registered: Option<u64>,
+}
+
+/// Response of `GET /apps`.
+#[derive(Serialize, Deserialize)]
+pub struct AppsResponse {
+    pub apps: Vec<RegisteredApp>,
+}
+
+/// Response of `/`.
+#[derive(Serialize, Deserialize)]
+pub struct InfoResponse {
+    pub version: String,
+    pub obs: String,
+}
+
+/// JSON body of `/recording/start`.
+/// Bodies that aren't sent as `application/json` and aren't an object with a `version` are read as the file name
+/// formatting instead, like old clients send it.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StartRequest {
+    /// The version of this schema, [`StartRequest::VERSION`] when missing
+    #[serde(default = "StartRequest::current_version")]
+    pub version: u32,
+    /// The file name formatting, refer to the OBS documentation for placeholders
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The directory the recording is saved to, which must exist
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub directory: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<RecordingFormat>,
+    /// Whether the recording is paused as soon as it starts
+    #[serde(default)]
+    pub pause_on_start: bool,
+}
+
+impl StartRequest {
+    /// The only version of the schema supported by this server.
+    pub const VERSION: u32 = 1;
+
+    fn current_version() -> u32 {
+        StartRequest::VERSION
+    }
+}
+
+impl Default for StartRequest {
+    fn default() -> Self {
+        StartRequest { version: StartRequest::VERSION, name: None, directory: None, format: None, pause_on_start: false }
+    }
+}
+
+/// A container format of the standard recording outputs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingFormat {
+    Mkv,
+    Mp4,
+    Flv,
+}
+
+impl RecordingFormat {
+    /// The file extension, which is how OBS stores the format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            RecordingFormat::Mkv => "mkv",
+            RecordingFormat::Mp4 => "mp4",
+            RecordingFormat::Flv => "flv",
+        }
+    }
+}
+
+/// Response of `/recording/stop`.
+#[derive(Serialize, Deserialize)]
+pub struct StopResponse {
+    pub path: Option<String>,
+    #[serde(default)]
+    pub markers: Vec<Marker>,
+}
+
+/// Body of `/recording/marker`.
+#[derive(Serialize, Deserialize)]
+pub struct MarkerRequest {
+    pub label: String,
+}
+
+/// A point of a recording, added through `/recording/marker`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Marker {
+    pub label: String,
+    /// Milliseconds from the start of the recording, excluding pauses
+    pub offset: u64,
+}
+
+/// Body of `/replay/save`.
+#[derive(Serialize, Deserialize, Default)]
+pub struct ReplayRequest {
+    /// The file name format of this replay, instead of the one of OBS
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// Response of `/replay/save`.
+#[derive(Serialize, Deserialize)]
+pub struct ReplayResponse {
+    pub path: Option<String>,
+}
+
+/// Response of `/recording/status`.
+#[derive(Serialize, Deserialize, Default)]
+pub struct RecordingStatus {
+    pub active: bool,
+    pub paused: bool,
+    /// Recording time in milliseconds, excluding pauses
+    pub elapsed: u64,
+    pub path: Option<String>,
+    /// Whether the recording was started with a custom file name formatting
+    pub custom_name: bool,
+    pub bytes: u64,
+    pub dropped_frames: u32,
+    pub total_frames: u32,
+}
+
+/// Response of `/streaming/status`.
+#[derive(Serialize, Deserialize, Default)]
+pub struct StreamingStatus {
+    pub active: bool,
+    /// Whether the connection was lost and OBS is trying to reconnect
+    pub reconnecting: bool,
+    /// Streaming time in milliseconds
+    pub uptime: u64,
+    /// From 0 (no congestion) to 1
+    pub congestion: f32,
+    pub bytes: u64,
+    pub dropped_frames: u32,
+    pub total_frames: u32,
+}
+
+/// Response of `/scenes`.
+#[derive(Serialize, Deserialize)]
+pub struct ScenesResponse {
+    pub scenes: Vec<String>,
+    /// The program scene
+    pub current: Option<String>,
+    /// The preview scene, in studio mode
+    pub preview: Option<String>,
+    pub studio_mode: bool,
+}
+
+/// Response of `/scenes/current` and `/scenes/preview`.
+#[derive(Serialize, Deserialize)]
+pub struct SceneResponse {
+    pub name: Option<String>,
+}
+
+/// Response of `/scene-collections`.
+#[derive(Serialize, Deserialize)]
+pub struct CollectionsResponse {
+    pub collections: Vec<String>,
+    pub current: Option<String>,
+}
+
+/// A source in a scene, listed by `/scenes/{scene}/sources`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SceneItem {
+    pub name: String,
+    pub visible: bool,
+}
+
+/// Response of `/scenes/{scene}/sources`.
+#[derive(Serialize, Deserialize)]
+pub struct SourcesResponse {
+    pub sources: Vec<SceneItem>,
+}
+
+/// Body of `/scenes/{scene}/sources/{source}/visible`.
+#[derive(Serialize, Deserialize)]
+pub struct VisibilityRequest {
+    pub visible: bool,
+}
+
+/// Body of `PUT /scenes/current`, `/scenes/preview` and `/scene-collections/current`.
+#[derive(Serialize, Deserialize)]
+pub struct NameRequest {
+    pub name: String,
+}
+
+/// Body of `/sources/{source}/mute`.
+#[derive(Serialize, Deserialize)]
+pub struct MuteRequest {
+    pub muted: bool,
+}
+
+/// The volume of an audio source. Requests set either field, responses set both.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Volume {
+    /// In decibels, `None` when the source is silenced
+    pub db: Option<f32>,
+    pub multiplier: Option<f32>,
+}
+
+impl Volume {
+    pub fn from_multiplier(multiplier: f32) -> Volume {
+        let db = if multiplier > 0.0 { Some(20.0 * multiplier.log10()) } else { None };
+        Volume { db, multiplier: Some(multiplier) }
+    }
+}
+
+/// Response of `/sources/{source}/audio`.
+#[derive(Serialize, Deserialize)]
+pub struct AudioResponse {
+    pub muted: bool,
+    pub volume: Volume,
+}
+
+/// Response of `/screenshot?format=base64`.
+#[derive(Serialize, Deserialize)]
+pub struct ScreenshotResponse {
+    pub width: u32,
+    pub height: u32,
+    /// The PNG image, in Base64
+    pub image: String,
+}
+
+/// Response carrying a status message.
+#[derive(Serialize, Deserialize)]
+pub struct MessageResponse {
+    pub message: String,
+}
+
+/// Response of every failed request.
+#[derive(Serialize, Deserialize)]
+pub struct ErrorResponse {
+    /// A stable, machine-readable code, e.g. `not_recording`
+    pub error: String,
+    /// A description of the error, for humans
+    pub message: String,
+}
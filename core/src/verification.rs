@@ -0,0 +1,687 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::convert::TryInto;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use ed25519_dalek::{Keypair, PublicKey, Signature, Verifier};
+use rand_core::{OsRng, RngCore};
+use ring::aead::{Aad, AES_256_GCM, LessSafeKey, Nonce, UnboundKey};
+use ring::hkdf;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::Visitor;
+use sha2::{Digest, Sha256};
+use tiny_http::Request;
+use x25519_dalek::EphemeralSecret;
+
+use crate::types::RegistrationResponse;
+use crate::verification::VerificationResult::{Body, JsonReject};
+use uuid::Uuid;
+use std::path::PathBuf;
+
+#[derive(Serialize, Deserialize)]
+pub struct AppMetadata {
+    uuid: u128,
+    name: String,
+    #[serde(serialize_with = "ser_pubkey", deserialize_with = "deser_pubkey")]
+    pub_key: PublicKey,
+    /// When the app was registered, in seconds since the Unix epoch; 0 for apps registered before dates were saved
+    registered: u64,
+}
+
+/// The signature scheme of requests with `X-OBSC-Version: 2`, which covers the [`canonical_request`].
+/// Requests without the header use the legacy scheme, which only covers the body.
+pub const PROTOCOL_VERSION: u32 = 2;
+/// The headers covered by the signature, in the order of the canonical request.
+pub const SIGNED_HEADERS: [&str; 5] = ["content-type", "x-obsc-app", "x-obsc-nonce", "x-obsc-timestamp", "x-obsc-version"];
+/// The registration protocol of apps sending `"version": 2` to `/register`, which wraps their secret key
+/// with a [`registration_key`] and a random nonce. Apps without a version use the legacy protocol,
+/// which uses the raw X25519 shared secret as the key and a zero nonce.
+pub const REGISTRATION_VERSION: u32 = 2;
+/// The start of the HKDF info of [`registration_key`].
+const REGISTRATION_LABEL: &[u8] = b"obs-controller registration v2";
+/// Characters left as is in the canonical path and query, the unreserved characters of RFC 3986.
+const UNRESERVED: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+/// How far the timestamp of a signed request can be from the server clock, in seconds.
+pub const MAX_CLOCK_SKEW: u64 = 300;
+/// How many nonces are remembered per app, which bounds the requests it can send within the clock skew window.
+const MAX_NONCES: usize = 1_000;
+/// The largest entry of the app store, which is only exceeded by a corrupted file.
+const MAX_ENTRY_SIZE: u64 = 4096;
+
+/// The file registered apps are appended to.
+pub struct AppStore {
+    path: PathBuf,
+    /// Held while writing the file, so rewriting it doesn't drop a concurrent registration
+    write_lock: Mutex<()>,
+    nonces: Mutex<NonceCache>,
+    /// Whether requests signed with the legacy scheme, which can be replayed, are accepted
+    legacy_signatures: AtomicBool
+}
+
+impl AppStore {
+    pub fn new<P: Into<PathBuf>>(path: P) -> AppStore {
+        AppStore { path: path.into(), write_lock: Mutex::default(), nonces: Mutex::default(), legacy_signatures: AtomicBool::new(false) }
+    }
+
+    /// Sets whether requests signed with the legacy scheme are accepted, which they aren't by default.
+    pub fn set_legacy_signatures(&self, allowed: bool) {
+        self.legacy_signatures.store(allowed, Ordering::Relaxed);
+    }
+
+    pub fn find(&self, uuid: u128) -> Result<Option<AppMetadata>, Error> {
+        let file = File::open(&self.path);
+        let mut file = match file {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e)
+        };
+        loop {
+            let entry_size = file.read_u64::<LittleEndian>();
+            return match entry_size {
+                Err(ref e) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+                Err(e) => Err(Error::new(ErrorKind::Other, e)),
+                Ok(entry_size) => {
+                    let entry_uuid = file.read_u128::<LittleEndian>()?;
+                    file.seek(SeekFrom::Current(-(std::mem::size_of::<u128>() as i64)))?;
+                    if uuid != entry_uuid {
+                        file.seek(SeekFrom::Current(entry_size as i64))?;
+                        continue;
+                    }
+                    read_entry(&mut file, entry_size).map(Some)
+                }
+            };
+        }
+    }
+
+    /// Reads every registered app, in registration order.
+    pub fn list(&self) -> Result<Vec<AppMetadata>, Error> {
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e)
+        };
+        let mut apps = Vec::new();
+        loop {
+            match file.read_u64::<LittleEndian>() {
+                Ok(entry_size) => apps.push(read_entry(&mut file, entry_size)?),
+                Err(ref e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(apps),
+                Err(e) => return Err(e)
+            }
+        }
+    }
+
+    /// Generates a key pair for a new app and appends it to the store.
+    /// Fails with [`ErrorKind::AlreadyExists`] if an app with the same UUID is registered.
+    pub fn register(&self, uuid: u128, name: String) -> Result<(AppMetadata, Keypair), Error> {
+        // Checked under the lock, so concurrent registrations of the same UUID can't both be appended
+        let _lock = self.write_lock.lock().unwrap_or_else(PoisonError::into_inner);
+        if self.find(uuid)?.is_some() {
+            return Err(Error::from(ErrorKind::AlreadyExists));
+        }
+        let pair = Keypair::generate(&mut OsRng);
+        let app = AppMetadata {
+            uuid,
+            name,
+            pub_key: pair.public,
+            registered: unix_time(),
+        };
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        write_entry(&mut file, &app)?;
+        Ok((app, pair))
+    }
+
+    /// Removes an app, which can register again afterwards. Returns whether it was registered.
+    ///
+    /// The store is written to a temporary file which then replaces it, so it is never left half-written.
+    pub fn revoke(&self, uuid: u128) -> Result<bool, Error> {
+        let _lock = self.write_lock.lock().unwrap_or_else(PoisonError::into_inner);
+        let apps = self.list()?;
+        if !apps.iter().any(|app| app.uuid == uuid) {
+            return Ok(false);
+        }
+        let temp = self.path.with_extension("ock.tmp");
+        let mut file = File::create(&temp)?;
+        for app in apps.iter().filter(|app| app.uuid != uuid) {
+            write_entry(&mut file, app)?;
+        }
+        file.sync_all()?;
+        drop(file);
+        fs::rename(&temp, &self.path)?;
+        Ok(true)
+    }
+}
+
+fn read_entry(file: &mut File, size: u64) -> Result<AppMetadata, Error> {
+    if size > MAX_ENTRY_SIZE {
+        return Err(Error::new(ErrorKind::InvalidData, "App store entry is too large"));
+    }
+    let mut entry = vec![0; size as usize];
+    file.read_exact(&mut entry)?;
+    // Entries written before registration dates were saved end after the public key, they are read with a date of 0
+    entry.extend_from_slice(&[0; 8]);
+    bincode::deserialize(&entry).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+fn write_entry(file: &mut File, app: &AppMetadata) -> Result<(), Error> {
+    let size = bincode::serialized_size(app).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    file.write_u64::<LittleEndian>(size)?;
+    bincode::serialize_into(file, app).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+/// The nonces of the recent signed requests, so each one is only accepted once.
+#[derive(Default)]
+struct NonceCache {
+    apps: HashMap<u128, AppNonces>
+}
+
+/// The nonces of the recent requests of an app.
+#[derive(Default)]
+struct AppNonces {
+    seen: HashSet<String>,
+    /// The same nonces with the timestamp of their request, which clients don't necessarily send in order
+    by_time: BTreeSet<(u64, String)>
+}
+
+impl NonceCache {
+    /// Remembers the nonce of a request, rejecting it if it was already used or if the app sent too many requests.
+    /// Nonces are forgotten once their request is outside the clock skew window.
+    fn insert(&mut self, app: u128, nonce: &str, timestamp: u64, now: u64) -> Result<(), VerificationResult> {
+        let oldest = now.saturating_sub(MAX_CLOCK_SKEW);
+        self.apps.retain(|_, nonces| {
+            nonces.forget_before(oldest);
+            !nonces.seen.is_empty()
+        });
+        let nonces = self.apps.entry(app).or_default();
+        if nonces.seen.contains(nonce) {
+            return Err(JsonReject(401, r#"{"error": "replayed_request", "message": "This request was already received"}"#));
+        }
+        if nonces.seen.len() >= MAX_NONCES {
+            return Err(JsonReject(429, r#"{"error": "too_many_requests", "message": "Too many signed requests, try again later"}"#));
+        }
+        nonces.seen.insert(nonce.to_string());
+        nonces.by_time.insert((timestamp, nonce.to_string()));
+        Ok(())
+    }
+}
+
+impl AppNonces {
+    /// Forgets the nonces of the requests sent before `oldest`.
+    fn forget_before(&mut self, oldest: u64) {
+        let recent = self.by_time.split_off(&(oldest, String::new()));
+        for (_, nonce) in std::mem::replace(&mut self.by_time, recent) {
+            self.seen.remove(&nonce);
+        }
+    }
+}
+
+/// Builds the string signed by requests of [`PROTOCOL_VERSION`], from the request URL (path and query)
+/// and its headers. The signature is an Ed25519 signature of the SHA-256 of this string, whose lines are:
+/// * `OBSC2`
+/// * The method, e.g. `POST`
+/// * The path, with empty segments removed and each segment percent-encoded except for RFC 3986 unreserved characters
+/// * The query parameters, decoded then encoded like the path, sorted and joined with `&`; empty without a query
+/// * One `name:value` line per header of [`SIGNED_HEADERS`], with a lowercase name and a trimmed value,
+///   which is empty if the header is missing
+/// * The hex SHA-256 of the body
+///
+/// The lines are separated by line feeds, without a trailing one.
+pub fn canonical_request(method: &str, url: &str, headers: &[(&str, &str)], body: &str) -> String {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let path: Vec<_> = path.split('/').filter(|segment| !segment.is_empty())
+        .map(|segment| utf8_percent_encode(&percent_decode_str(segment).decode_utf8_lossy(), UNRESERVED).to_string())
+        .collect();
+    let mut query: Vec<_> = form_urlencoded::parse(query.as_bytes())
+        .map(|(name, value)| format!("{}={}", utf8_percent_encode(&name, UNRESERVED), utf8_percent_encode(&value, UNRESERVED)))
+        .collect();
+    query.sort();
+    let mut canonical = format!("OBSC2\n{}\n/{}\n{}\n", method, path.join("/"), query.join("&"));
+    for name in &SIGNED_HEADERS {
+        let value = headers.iter().find(|(header, _)| header.eq_ignore_ascii_case(name)).map_or("", |(_, value)| value);
+        canonical.push_str(&format!("{}:{}\n", name, value.trim()));
+    }
+    canonical.push_str(&format!("{:x}", Sha256::digest(body.as_bytes())));
+    canonical
+}
+
+/// The current time, in seconds since the Unix epoch.
+pub fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs())
+}
+
+pub enum VerificationResult {
+    /// Verification successful, return the parsed body
+    Body(String),
+    /// Verification failed, respond instantly with a status code + message
+    JsonReject(u16, &'static str)
+}
+
+/// Parses an HTTP request, checks if the app is authenticated and returns the body if so
+pub fn middleware_auth(req: &mut Request, apps: &AppStore) -> VerificationResult {
+    let mut body = String::with_capacity(1024.min(req.body_length().unwrap_or(1024)));
+    if req.as_reader().take(1024).read_to_string(&mut body).is_err() {
+        return JsonReject(400, r#"{"error": "invalid_body", "message": "The body must be valid UTF-8"}"#);
+    }
+    verify_request(req, apps, body)
+}
+
+/// Checks if the app is authenticated, using a body that was already read.
+/// Useful for requests whose body must not be read, like WebSocket handshakes.
+pub fn verify_request(req: &Request, apps: &AppStore, body: String) -> VerificationResult {
+    if !cfg!(feature = "verification") {
+        return VerificationResult::Body(body)
+    }
+    match verify_app(req, apps, body) {
+        Ok(res) => res,
+        Err(e) => {
+            log::error!("Couldn't read the app store {:?}", e);
+            JsonReject(500, r#"{"error": "internal_error", "message": "The request couldn't be fulfilled due to an error"}"#)
+        }
+    }
+}
+
+fn header<'a>(req: &'a Request, name: &'static str) -> Option<&'a str> {
+    req.headers().iter().find(|h| h.field.equiv(name)).map(|h| h.value.as_str())
+}
+
+/// The app sending a request, which is only authenticated once [`middleware_auth`] accepted it.
+pub fn request_app(req: &Request) -> Option<u128> {
+    header(req, "X-OBSC-App").and_then(|app| Uuid::parse_str(app).ok()).map(uuid_to_u128)
+}
+
+/// Whether a request is signed with the legacy scheme, which only covers the body and can be replayed.
+pub fn is_legacy_signature(req: &Request) -> bool {
+    matches!(header(req, "X-OBSC-Version"), None | Some("1"))
+}
+
+fn verify_app(req: &Request, apps: &AppStore, body: String) -> Result<VerificationResult, Error> {
+    let app = header(req, "X-OBSC-App").map(Uuid::parse_str);
+    let signature = header(req, "X-OBSC-Signature").map(base64::decode);
+    match (&app, &signature) {
+        (Some(Err(_)), _) => {
+            return Ok(JsonReject(400, r#"{"error": "invalid_app", "message": "Invalid UUID in X-OBSC-App"}"#));
+        }
+        (_, Some(Err(_))) => {
+            return Ok(JsonReject(400, r#"{"error": "invalid_signature", "message": "Invalid Base64 in X-OBSC-Signature"}"#));
+        },
+        (Some(_), Some(_)) => {}
+        _ => {
+            return Ok(JsonReject(400, r#"{"error": "missing_credentials", "message": "Missing X-OBSC-App or X-OBSC-Signature"}"#));
+        }
+    }
+    let (msg, nonce) = match header(req, "X-OBSC-Version") {
+        None | Some("1") => {
+            if !apps.legacy_signatures.load(Ordering::Relaxed) {
+                return Ok(JsonReject(401, r#"{"error": "legacy_signature", "message": "Requests must be signed with protocol version 2"}"#));
+            }
+            (if body.is_empty() { "obs-controller".to_string() } else { body.clone() }, None)
+        }
+        Some("2") => {
+            let timestamp = match header(req, "X-OBSC-Timestamp").and_then(|time| time.parse::<u64>().ok()) {
+                Some(timestamp) => timestamp,
+                None => return Ok(JsonReject(400, r#"{"error": "invalid_timestamp", "message": "Missing or invalid X-OBSC-Timestamp"}"#))
+            };
+            let now = unix_time();
+            if timestamp.max(now) - timestamp.min(now) > MAX_CLOCK_SKEW {
+                return Ok(JsonReject(401, r#"{"error": "clock_skew", "message": "X-OBSC-Timestamp is too far from the server time"}"#));
+            }
+            let nonce = match header(req, "X-OBSC-Nonce").filter(|nonce| (16..=128).contains(&nonce.len())) {
+                Some(nonce) => nonce,
+                None => return Ok(JsonReject(400, r#"{"error": "invalid_nonce", "message": "X-OBSC-Nonce must be 16 to 128 characters long"}"#))
+            };
+            let headers: Vec<_> = req.headers().iter().map(|h| (h.field.as_str().as_str(), h.value.as_str())).collect();
+            (canonical_request(&req.method().to_string(), req.url(), &headers, &body), Some((nonce, timestamp, now)))
+        }
+        Some(_) => return Ok(JsonReject(400, r#"{"error": "unsupported_protocol", "message": "Unsupported X-OBSC-Version"}"#))
+    };
+    let uuid = uuid_to_u128(app.unwrap().unwrap());
+    let app = match apps.find(uuid)? {
+        Some(app) => app,
+        None => {
+            return Ok(JsonReject(400, r#"{"error": "unknown_app", "message": "Unknown app"}"#));
+        }
+    };
+    let signature = signature.unwrap().unwrap();
+    if signature.len() != 64 {
+        return Ok(JsonReject(400, r#"{"error": "invalid_signature", "message": "Signature must be 64 bytes in length."}"#));
+    }
+    if app.validate_message(msg.as_bytes(), signature.try_into().unwrap()) {
+        // Only authenticated requests can take up room in the cache
+        match nonce {
+            Some((nonce, timestamp, now)) => {
+                if let Err(reject) = apps.nonces.lock().unwrap_or_else(PoisonError::into_inner).insert(uuid, nonce, timestamp, now) {
+                    return Ok(reject);
+                }
+            }
+            None => log::warn!("{} ({}) signed a request with the deprecated legacy protocol, which can be replayed",
+                               app.name, Uuid::from_u128(uuid))
+        }
+        Ok(Body(body))
+    } else {
+        Ok(JsonReject(401, r#"{"error": "not_authenticated", "message": "Not authenticated"}"#))
+    }
+}
+
+/// Asks the user whether an app is allowed to access OBS.
+pub trait Approver: Send + Sync {
+    /// Blocks until the user has accepted or denied the app.
+    fn approve(&self, name: &str) -> bool;
+}
+
+impl<F> Approver for F where F: Fn(&str) -> bool + Send + Sync {
+    fn approve(&self, name: &str) -> bool {
+        self(name)
+    }
+}
+
+/// Registers an app, returning its private key for Ed25519 message signing, encrypted with the given registration
+/// protocol (1 or [`REGISTRATION_VERSION`]), and the server's X25519 public key.
+pub fn register_encrypt(apps: &AppStore, uuid: Uuid, name: String, their_pubkey: x25519_dalek::PublicKey, version: u32) -> Result<RegistrationResponse, Error> {
+    let (_, key) = apps.register(uuid_to_u128(uuid), name)?;
+    let our_secret = EphemeralSecret::new(rand_core::OsRng);
+    let our_pubkey = x25519_dalek::PublicKey::from(&our_secret);
+    // Compute the shared secret from the app's public key and our generated secret
+    let shared = our_secret.diffie_hellman(&their_pubkey);
+    let (aes, nonce) = if version == REGISTRATION_VERSION {
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce);
+        (registration_key(shared.as_bytes(), uuid, their_pubkey.as_bytes(), our_pubkey.as_bytes()), Some(nonce))
+    } else {
+        (LessSafeKey::new(UnboundKey::new(&AES_256_GCM, shared.as_bytes()).expect("Couldn't create key")), None)
+    };
+    // Encrypt the app's Ed25519 private key for communication
+    let mut secret = [0u8; 32 + 16]; // tag length = 16
+    secret[..32].copy_from_slice(&key.secret.to_bytes());
+    let tag = aes.seal_in_place_separate_tag(Nonce::assume_unique_for_key(nonce.unwrap_or([0; 12])), Aad::empty(), &mut secret[..32])
+        .expect("Couldn't create tag");
+    secret[32..].copy_from_slice(tag.as_ref());
+    Ok(RegistrationResponse {
+        version: if nonce.is_some() { REGISTRATION_VERSION } else { 1 },
+        key: base64::encode(secret),
+        shared_public: base64::encode(our_pubkey.to_bytes()),
+        nonce: nonce.map(base64::encode),
+    })
+}
+
+/// Derives the AES-256-GCM key wrapping the app's secret key with [`REGISTRATION_VERSION`]:
+/// HKDF-SHA256 of the X25519 shared secret without salt, with the info `obs-controller registration v2`
+/// followed by the UUID bytes of the app, its X25519 public key and the server's X25519 public key.
+pub fn registration_key(shared: &[u8; 32], uuid: Uuid, app_public: &[u8; 32], server_public: &[u8; 32]) -> LessSafeKey {
+    let info: [&[u8]; 4] = [REGISTRATION_LABEL, uuid.as_bytes(), app_public, server_public];
+    let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, &[]).extract(shared);
+    let okm = prk.expand(&info, &AES_256_GCM).expect("Invalid HKDF output length");
+    LessSafeKey::new(okm.into())
+}
+
+/// Converts a UUID to the representation used in the app store.
+pub fn uuid_to_u128(uuid: Uuid) -> u128 {
+    uuid.to_u128_le()
+}
+
+impl AppMetadata {
+    pub fn uuid(&self) -> Uuid {
+        Uuid::from_u128_le(self.uuid)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// When the app was registered, in seconds since the Unix epoch, unless it was registered before dates were saved.
+    pub fn registered(&self) -> Option<u64> {
+        Some(self.registered).filter(|&time| time > 0)
+    }
+
+    fn validate_message(&self, message: &[u8], signature: [u8; 64]) -> bool {
+        let mut hash = Sha256::new();
+        hash.update(message);
+        self.pub_key.verify(&hash.finalize()[..], &Signature::from(signature)).is_ok()
+    }
+}
+
+fn ser_pubkey<S>(pub_key: &PublicKey, ser: S) -> Result<S::Ok, S::Error> where S: Serializer {
+    ser.serialize_bytes(pub_key.as_bytes())
+}
+
+struct PubkeyVisitor;
+
+impl<'de> Visitor<'de> for PubkeyVisitor {
+    type Value = PublicKey;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("public key bytes")
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E> where E: serde::de::Error {
+        PublicKey::from_bytes(v).map_err(|e| serde::de::Error::custom(format!("Invalid public key: {:?}", e)))
+    }
+}
+
+fn deser_pubkey<'de, D>(deser: D) -> Result<PublicKey, D::Error> where D: Deserializer<'de> {
+    deser.deserialize_bytes(PubkeyVisitor)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io::ErrorKind;
+    use std::path::PathBuf;
+
+    use ed25519_dalek::{SecretKey, Signature, Signer};
+    use rand_core::OsRng;
+    use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM};
+    use sha2::{Digest, Sha256};
+    use x25519_dalek::{EphemeralSecret, PublicKey};
+
+    use crate::verification::{canonical_request, register_encrypt, registration_key, uuid_to_u128, REGISTRATION_VERSION, NonceCache, VerificationResult, MAX_CLOCK_SKEW, MAX_NONCES};
+
+    use super::{AppMetadata, AppStore};
+    use uuid::Uuid;
+
+    /// A file in the temp directory, removed on drop.
+    pub(crate) struct TempFile(PathBuf);
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// Creates an empty store in the temp directory, which is removed when the returned [`TempFile`] is dropped.
+    pub(crate) fn temp_store() -> (AppStore, TempFile) {
+        use rand_core::RngCore;
+        let path = std::env::temp_dir().join(format!("obs-controller-test-{:x}.ock", OsRng.next_u64()));
+        (AppStore::new(path.clone()), TempFile(path))
+    }
+
+    #[test]
+    pub fn validate() {
+        let (store, _file) = temp_store();
+        let (app, key) = store.register(0, "Test".to_string()).unwrap();
+        // Calculate the payload hash (provided by the client in a real scenario)
+        let mut hash = Sha256::new();
+        hash.update(b"Test message signed");
+        // Verify the payload with the signature (calculated here, provided by the client in a real scenario)
+        let signature: Signature = key.sign(&hash.finalize()[..]);
+        assert!(app.validate_message(b"Test message signed", signature.to_bytes()));
+    }
+
+    #[test]
+    pub fn canonical_vectors() {
+        let headers = [
+            ("X-OBSC-App", "67e55044-10b1-426f-9247-bb680e5fe0c8"),
+            ("x-obsc-nonce", "bm9uY2UtMDEyMzQ1Njc4OQ=="),
+            ("X-OBSC-Timestamp", " 1700000000 "),
+            ("X-OBSC-Version", "2"),
+            ("X-OBSC-Signature", "ignored"),
+            ("Content-Type", "application/json"),
+        ];
+        let post = canonical_request("POST", "/recording/start", &headers, r#"{"name":"Take 1"}"#);
+        assert_eq!(post, "OBSC2\nPOST\n/recording/start\n\n\
+            content-type:application/json\nx-obsc-app:67e55044-10b1-426f-9247-bb680e5fe0c8\nx-obsc-nonce:bm9uY2UtMDEyMzQ1Njc4OQ==\n\
+            x-obsc-timestamp:1700000000\nx-obsc-version:2\n\
+            1bbdb822fa8802812ece2aac492bdd8ab8cccdda27970054fefe0423c0359315");
+        // Paths are normalized and query parameters sorted, whatever their encoding
+        let get = canonical_request("GET", "//screenshot/?width=320&source=Game+Capture&height=180", &headers, "");
+        assert_eq!(get, canonical_request("GET", "/screenshot?height=180&source=Game%20Capture&width=320", &headers, ""));
+        assert_eq!(get, "OBSC2\nGET\n/screenshot\nheight=180&source=Game%20Capture&width=320\n\
+            content-type:application/json\nx-obsc-app:67e55044-10b1-426f-9247-bb680e5fe0c8\nx-obsc-nonce:bm9uY2UtMDEyMzQ1Njc4OQ==\n\
+            x-obsc-timestamp:1700000000\nx-obsc-version:2\n\
+            e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(canonical_request("GET", "/sources/Game%20Capture%2Fv2", &[], ""), "OBSC2\nGET\n/sources/Game%20Capture%2Fv2\n\n\
+            content-type:\nx-obsc-app:\nx-obsc-nonce:\nx-obsc-timestamp:\nx-obsc-version:\n\
+            e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        // Signature of the SHA-256 of the canonical request, as sent by clients
+        let secret = SecretKey::from_bytes(&[7; 32]).unwrap();
+        let key = ed25519_dalek::Keypair { public: (&secret).into(), secret };
+        for (canonical, expected) in &[(post, "aQgyTd3gxHW93LhpDkSgNlV96cunRqtdOOIxH7A2DlIkuGkCB4HEQ+V1WLLWE0SYoxWqTTuk/3hLwetBlDrcCA=="), (get, "RdJzq6Gve7GA4DYEwuFLqs2NyDROOOJZ0psi0rstDkKDJy3OvgMQ/mfFLbDFCEbTqc8Pld7zSyRDBgni/s60Cg==")] {
+            let signature = key.sign(&Sha256::digest(canonical.as_bytes())[..]);
+            assert_eq!(base64::encode(signature.to_bytes()), *expected);
+        }
+    }
+
+    #[test]
+    pub fn nonces() {
+        let mut nonces = NonceCache::default();
+        assert!(nonces.insert(1, "nonce-0123456789", 1000, 1000).is_ok());
+        assert!(nonces.insert(1, "nonce-0123456789", 1000, 1001).is_err());
+        // Nonces are per app
+        assert!(nonces.insert(2, "nonce-0123456789", 1000, 1001).is_ok());
+        // Requests aren't necessarily received in timestamp order
+        assert!(nonces.insert(1, "nonce-later", 1200, 1001).is_ok());
+        assert!(nonces.insert(1, "nonce-earlier", 900, 1001).is_ok());
+        // Once the request is outside the window, its timestamp is rejected before the nonce is checked
+        let now = 1000 + MAX_CLOCK_SKEW + 1;
+        assert!(nonces.insert(1, "nonce-0123456789", now, now).is_ok());
+        let mut seen: Vec<_> = nonces.apps[&1].seen.iter().map(String::as_str).collect();
+        seen.sort_unstable();
+        assert_eq!(vec!["nonce-0123456789", "nonce-later"], seen);
+        assert!(!nonces.apps.contains_key(&2));
+        // An app sending too many requests doesn't lock the other ones out
+        for i in 2..MAX_NONCES {
+            assert!(nonces.insert(1, &i.to_string(), now, now).is_ok());
+        }
+        assert!(matches!(nonces.insert(1, "full", now, now), Err(VerificationResult::JsonReject(429, _))));
+        assert!(nonces.insert(3, "nonce-0123456789", now, now).is_ok());
+    }
+
+    #[test]
+    pub fn parse() {
+        let (store, _file) = temp_store();
+        store.register(12, "Test Parse".to_string()).unwrap();
+        assert_eq!(12, store.find(12).unwrap().unwrap().uuid);
+        let duplicate = store.register(12, "Duplicate".to_string()).map(|_| ()).unwrap_err();
+        assert_eq!(ErrorKind::AlreadyExists, duplicate.kind());
+        assert_eq!(1, store.list().unwrap().len());
+    }
+
+    #[test]
+    pub fn revoke() {
+        let (store, _file) = temp_store();
+        for (uuid, name) in &[(1, "First"), (2, "Second"), (3, "Third")] {
+            store.register(*uuid, name.to_string()).unwrap();
+        }
+        assert!(store.revoke(2).unwrap());
+        assert!(!store.revoke(2).unwrap());
+        assert!(store.find(2).unwrap().is_none());
+        let apps = store.list().unwrap();
+        assert_eq!(vec!["First", "Third"], apps.iter().map(AppMetadata::name).collect::<Vec<_>>());
+        assert!(apps[0].registered().unwrap() > 0);
+        // Revoked apps can register again
+        store.register(2, "Second".to_string()).unwrap();
+        assert_eq!(3, store.find(3).unwrap().unwrap().uuid);
+        assert_eq!(3, store.list().unwrap().len());
+    }
+
+    #[test]
+    pub fn legacy_entries() {
+        let (store, _file) = temp_store();
+        let (app, _) = store.register(5, "Legacy".to_string()).unwrap();
+        // Entries used to end after the public key
+        let mut entry = bincode::serialize(&app).unwrap();
+        entry.truncate(entry.len() - 8);
+        let (store, _file) = temp_store();
+        let mut file = Vec::new();
+        file.extend_from_slice(&(entry.len() as u64).to_le_bytes());
+        file.extend_from_slice(&entry);
+        std::fs::write(&store.path, file).unwrap();
+        let app = store.find(5).unwrap().unwrap();
+        assert_eq!(("Legacy", None), (app.name(), app.registered()));
+        store.register(6, "Current".to_string()).unwrap();
+        assert!(store.revoke(5).unwrap());
+        assert!(store.find(6).unwrap().unwrap().registered().is_some());
+    }
+
+    #[test]
+    pub fn uuid() {
+        let uuid = Uuid::parse_str("98704291-09e9-40f2-8476-064521fadaff").unwrap();
+        assert_eq!(340090132878606694826081478218872942744_u128, uuid_to_u128(uuid));
+    }
+
+    #[test]
+    pub fn get_private_key() {
+        let secret = EphemeralSecret::new(OsRng);
+        let pub_key = PublicKey::from(&secret);
+        let (store, _file) = temp_store();
+        let res = register_encrypt(&store, Uuid::parse_str("98704291-09e9-40f2-8476-064521fadaff").unwrap(), String::from("Test"), pub_key, 1).unwrap();
+        assert_eq!((1, None), (res.version, res.nonce));
+        let mut server_pub = [0u8; 32];
+        server_pub.copy_from_slice(&base64::decode(res.shared_public).unwrap());
+        let server_pub = PublicKey::from(server_pub);
+        let shared = secret.diffie_hellman(&server_pub);
+        let aes = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, shared.as_bytes()).unwrap());
+        let mut priv_bytes = [0u8; 48];
+        priv_bytes.copy_from_slice(&base64::decode(res.key).unwrap());
+        aes.open_in_place(Nonce::assume_unique_for_key([0; 12]), Aad::empty(), &mut priv_bytes).unwrap();
+        SecretKey::from_bytes(&priv_bytes[..32]).unwrap();
+    }
+
+    #[test]
+    pub fn get_private_key_hkdf() {
+        let uuid = Uuid::parse_str("98704291-09e9-40f2-8476-064521fadaff").unwrap();
+        let secret = EphemeralSecret::new(OsRng);
+        let pub_key = PublicKey::from(&secret);
+        let (store, _file) = temp_store();
+        let res = register_encrypt(&store, uuid, String::from("Test"), pub_key, REGISTRATION_VERSION).unwrap();
+        assert_eq!(REGISTRATION_VERSION, res.version);
+        let mut server_pub = [0u8; 32];
+        server_pub.copy_from_slice(&base64::decode(res.shared_public).unwrap());
+        let shared = secret.diffie_hellman(&PublicKey::from(server_pub));
+        let mut nonce = [0u8; 12];
+        nonce.copy_from_slice(&base64::decode(res.nonce.unwrap()).unwrap());
+        let encrypted = base64::decode(res.key).unwrap();
+        let open = |uuid: Uuid, nonce: [u8; 12]| {
+            let mut priv_bytes = encrypted.clone();
+            let aes = registration_key(shared.as_bytes(), uuid, pub_key.as_bytes(), &server_pub);
+            aes.open_in_place(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut priv_bytes).map(|key| key.to_vec())
+        };
+        SecretKey::from_bytes(&open(uuid, nonce).unwrap()).unwrap();
+        // The key is bound to the app and the nonce
+        assert!(open(Uuid::nil(), nonce).is_err());
+        assert!(open(uuid, [0; 12]).is_err());
+    }
+}
\ No newline at end of file
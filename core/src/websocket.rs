@@ -0,0 +1,129 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! The `/events` WebSocket endpoint.
+//! Messages from clients aren't read: the server only pushes events and pings.
+
+use std::io::{self, Write};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use sha1::{Digest, Sha1};
+use tiny_http::{Header, Response};
+
+use crate::events::Event;
+use crate::server::{json_response, Responder};
+use crate::verification::{self, VerificationResult};
+use crate::Controller;
+
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// Pings let us notice disconnected clients even when there are no events.
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+const OP_TEXT: u8 = 0x1;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+/// Close status sent when the plugin is unloaded
+const GOING_AWAY: u16 = 1001;
+
+/// Computes the `Sec-WebSocket-Accept` value for a `Sec-WebSocket-Key`.
+pub fn accept_key(key: &str) -> String {
+    let mut hash = Sha1::new();
+    hash.update(key.as_bytes());
+    hash.update(GUID.as_bytes());
+    base64::encode(hash.finalize())
+}
+
+/// Completes the handshake, then streams events to the client on a dedicated thread.
+pub fn serve_events(req: Responder, controller: Arc<Controller>) -> io::Result<()> {
+    let header = |name: &'static str| req.headers().iter().find(|h| h.field.equiv(name)).map(|h| h.value.as_str().to_string());
+    let is_upgrade = header("Upgrade").map_or(false, |v| v.eq_ignore_ascii_case("websocket"));
+    let key = match header("Sec-WebSocket-Key") {
+        Some(key) if is_upgrade => key,
+        _ => {
+            let upgrade = Header::from_bytes(&b"Upgrade"[..], &b"websocket"[..]).unwrap();
+            return req.respond(json_response(426, r#"{"error": "websocket_required", "message": "Expected a WebSocket handshake"}"#).with_header(upgrade));
+        }
+    };
+    // The handshake has no body, and reading it would block on the WebSocket stream
+    if let VerificationResult::JsonReject(status, msg) = verification::verify_request(&req, &controller.apps, String::new()) {
+        return req.respond(json_response(status, msg));
+    }
+    let events = controller.events.subscribe();
+    let accept = Header::from_bytes(&b"Sec-WebSocket-Accept"[..], accept_key(&key).as_bytes()).unwrap();
+    let mut stream = req.into_request().upgrade("websocket", Response::empty(101).with_header(accept));
+    thread::spawn(move || {
+        if let Err(e) = stream_events(&mut stream, events) {
+            log::debug!("Event client disconnected: {}", e);
+        }
+    });
+    Ok(())
+}
+
+fn stream_events(stream: &mut dyn Write, events: Receiver<Arc<Event>>) -> io::Result<()> {
+    loop {
+        match events.recv_timeout(PING_INTERVAL) {
+            Ok(event) => write_frame(stream, OP_TEXT, serde_json::to_string(&*event)?.as_bytes())?,
+            Err(RecvTimeoutError::Timeout) => write_frame(stream, OP_PING, &[])?,
+            Err(RecvTimeoutError::Disconnected) => return write_frame(stream, OP_CLOSE, &GOING_AWAY.to_be_bytes())
+        }
+    }
+}
+
+/// Writes a single, unmasked frame, as sent by servers.
+fn write_frame(stream: &mut dyn Write, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    let mut header = vec![0x80 | opcode];
+    match payload.len() {
+        len if len < 126 => header.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            header.push(126);
+            header.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            header.push(127);
+            header.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    stream.write_all(&header)?;
+    stream.write_all(payload)?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{accept_key, write_frame, OP_TEXT};
+
+    #[test]
+    pub fn accept() {
+        // From RFC 6455
+        assert_eq!("s3pPLMBiTxaQ9kYGzzhZRbK+xOo=", accept_key("dGhlIHNhbXBsZSBub25jZQ=="));
+    }
+
+    #[test]
+    pub fn frames() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, OP_TEXT, b"Hello").unwrap();
+        assert_eq!(b"\x81\x05Hello".to_vec(), buf);
+        buf.clear();
+        write_frame(&mut buf, OP_TEXT, &[b'a'; 300]).unwrap();
+        assert_eq!([0x81, 126, 1, 44], buf[..4]);
+        assert_eq!(304, buf.len());
+    }
+}
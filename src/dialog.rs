@@ -20,13 +20,19 @@ use cpp::cpp;
 use qt_core::QString;
 use qt_widgets::QMessageBox;
 use qt_widgets::q_message_box::StandardButton;*/
+use obs_controller_core::verification::{self, AppMetadata};
+use obs_controller_core::{Approver, AppStore};
 use std::ffi::CString;
-use std::sync::mpsc::Sender;
+use std::os::raw::c_char;
+use std::sync::mpsc::{self, Sender};
 
 cpp!{{
     #include <QtCore/QCoreApplication>
+    #include <QtCore/QDateTime>
     #include <QtCore/QMetaObject>
     #include <QtCore/QString>
+    #include <QtCore/QStringList>
+    #include <QtWidgets/QInputDialog>
     #include <QtWidgets/QMessageBox>
 }}
 
@@ -50,7 +56,8 @@ pub struct AppInfo {
 
 impl AppInfo {
     pub fn new(name: String) -> Box<AppInfo> {
-        Box::new(Self {name: CString::new(name).unwrap()})
+        // Registrations are rejected by the core if the name contains a NUL character
+        Box::new(Self {name: CString::new(name).unwrap_or_default()})
     }
 }
 
@@ -76,10 +83,42 @@ impl Dialog {
     }
 }
 
+/// Asks for app approval with a Qt dialog.
+pub struct DialogApprover;
+
+impl Approver for DialogApprover {
+    fn approve(&self, name: &str) -> bool {
+        let (tx, rx) = mpsc::channel();
+        Dialog::new(AppInfo::new(name.to_string()), Box::new(tx)).open();
+        matches!(rx.recv(), Ok(DialogResult::Accepted(_)))
+    }
+}
+
+/// Lets the user pick a registered app and revoke it. Called from the Tools menu, on the main thread.
+pub fn manage_apps(apps: &AppStore) {
+    let registered = match apps.list() {
+        Ok(registered) => registered,
+        Err(e) => return show_message(&format!("Couldn't read the registered apps: {}", e))
+    };
+    if registered.is_empty() {
+        return show_message("No app is registered.");
+    }
+    let app = match select_app(&registered) {
+        Some(index) => &registered[index],
+        None => return
+    };
+    if !confirm(&format!("Revoke {}? It will have to register again to access OBS.", app.name())) {
+        return;
+    }
+    if let Err(e) = apps.revoke(verification::uuid_to_u128(app.uuid())) {
+        show_message(&format!("Couldn't revoke {}: {}", app.name(), e));
+    }
+}
+
 #[allow(clippy::boxed_local)]
 fn open_dialog(app_info: Box<AppInfo>, sender: Box<Sender<DialogResult>>) {
     let res = unsafe {
-        let desc = CString::new(format!("Allow {} to access OBS?", app_info.name.to_str().unwrap())).unwrap();
+        let desc = CString::new(format!("Allow {} to access OBS?", app_info.name.to_string_lossy())).unwrap_or_default();
         let desc_ptr = desc.as_ptr();
         let title_ptr = app_info.name.as_ptr();
         cpp!([desc_ptr as "const char*", title_ptr as "const char*"] -> u32 as "int32_t" {
@@ -93,5 +132,50 @@ fn open_dialog(app_info: Box<AppInfo>, sender: Box<Sender<DialogResult>>) {
             return msgBox.exec();
         })
     };
-    sender.send(if res == 65536 {DialogResult::Denied} else {DialogResult::Accepted(app_info)}).unwrap();
+    // The request is gone if its worker was stopped in the meantime
+    let _ = sender.send(if res == 65536 {DialogResult::Denied} else {DialogResult::Accepted(app_info)});
+}
+
+/// Lists the apps with their registration date, returning the index of the selected one.
+/// Each item includes the app's UUID, so apps with the same name and date can still be told apart.
+fn select_app(apps: &[AppMetadata]) -> Option<usize> {
+    let names: Vec<CString> = apps.iter().map(|app| CString::new(app.name()).unwrap_or_default()).collect();
+    let name_ptrs: Vec<*const c_char> = names.iter().map(|name| name.as_ptr()).collect();
+    let uuids: Vec<CString> = apps.iter().map(|app| CString::new(app.uuid().to_string()).unwrap_or_default()).collect();
+    let uuid_ptrs: Vec<*const c_char> = uuids.iter().map(|uuid| uuid.as_ptr()).collect();
+    let dates: Vec<u64> = apps.iter().map(|app| app.registered().unwrap_or(0)).collect();
+    let (names_ptr, uuids_ptr, dates_ptr, len) = (name_ptrs.as_ptr(), uuid_ptrs.as_ptr(), dates.as_ptr(), apps.len());
+    let index = unsafe {
+        cpp!([names_ptr as "const char* const*", uuids_ptr as "const char* const*", dates_ptr as "const uint64_t*", len as "size_t"] -> i32 as "int32_t" {
+            QStringList items;
+            for (size_t i = 0; i < len; i++) {
+                QString date = dates_ptr[i] ? QDateTime::fromSecsSinceEpoch(dates_ptr[i]).toString(Qt::DefaultLocaleShortDate) : QString("an unknown date");
+                items << QString("%1 (registered on %2, %3)").arg(QString(names_ptr[i]), date, QString(uuids_ptr[i]));
+            }
+            bool ok = false;
+            QString item = QInputDialog::getItem(nullptr, "OBS Controller", "Select an app to revoke:", items, 0, false, &ok);
+            return ok ? items.indexOf(item) : -1;
+        })
+    };
+    if index < 0 { None } else { Some(index as usize) }
+}
+
+fn confirm(text: &str) -> bool {
+    let text = CString::new(text).unwrap_or_default();
+    let text_ptr = text.as_ptr();
+    unsafe {
+        cpp!([text_ptr as "const char*"] -> bool as "bool" {
+            return QMessageBox::question(nullptr, "OBS Controller", QString(text_ptr)) == QMessageBox::StandardButton::Yes;
+        })
+    }
+}
+
+fn show_message(text: &str) {
+    let text = CString::new(text).unwrap_or_default();
+    let text_ptr = text.as_ptr();
+    unsafe {
+        cpp!([text_ptr as "const char*"] {
+            QMessageBox::information(nullptr, "OBS Controller", QString(text_ptr));
+        })
+    }
 }
\ No newline at end of file
@@ -0,0 +1,420 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_void};
+
+use obs_controller_core::events::EventKind;
+use obs_controller_core::backend::{AudioState, OutputStats};
+use obs_controller_core::types::SceneItem;
+use obs_controller_core::ObsBackend;
+
+use crate::obs;
+
+/// [`ObsBackend`] implementation that calls into the running OBS instance.
+pub struct ObsFrontend;
+
+impl ObsBackend for ObsFrontend {
+    fn version(&self) -> String {
+        unsafe { to_string(obs::obs_get_version_string()) }.unwrap_or_default()
+    }
+
+    fn recording_start(&self) {
+        unsafe { obs::obs_frontend_recording_start() }
+    }
+
+    fn recording_stop(&self) {
+        unsafe { obs::obs_frontend_recording_stop() }
+    }
+
+    fn recording_active(&self) -> bool {
+        unsafe { obs::obs_frontend_recording_active() }
+    }
+
+    fn recording_paused(&self) -> bool {
+        unsafe { obs::obs_frontend_recording_paused() }
+    }
+
+    fn recording_can_pause(&self) -> bool {
+        unsafe {
+            let output = obs::obs_frontend_get_recording_output();
+            if output.is_null() {
+                return false;
+            }
+            let output = FileOutput(output);
+            obs::obs_output_can_pause(output.0)
+        }
+    }
+
+    fn recording_pause(&self, pause: bool) {
+        unsafe { obs::obs_frontend_recording_pause(pause) }
+    }
+
+    fn recording_stats(&self) -> Option<OutputStats> {
+        unsafe { output_stats(obs::obs_frontend_get_recording_output()) }
+    }
+
+    fn profile_config_get(&self, section: &str, name: &str) -> Option<String> {
+        let (section, name) = (c_string(section)?, c_string(name)?);
+        unsafe {
+            let config = obs::obs_frontend_get_profile_config();
+            to_string(obs::config_get_string(config, section.as_ptr(), name.as_ptr()))
+        }
+    }
+
+    fn profile_config_override(&self, section: &str, name: &str, value: &str) {
+        if let (Some(section), Some(name), Some(value)) = (c_string(section), c_string(name), c_string(value)) {
+            unsafe {
+                obs::config_set_string(obs::obs_frontend_get_profile_config(), section.as_ptr(), name.as_ptr(), value.as_ptr());
+            }
+        }
+    }
+
+    fn profile_config_remove(&self, section: &str, name: &str) {
+        if let (Some(section), Some(name)) = (c_string(section), c_string(name)) {
+            unsafe {
+                obs::config_remove_value(obs::obs_frontend_get_profile_config(), section.as_ptr(), name.as_ptr());
+            }
+        }
+    }
+
+    fn recording_output_setting(&self, name: &str) -> Option<String> {
+        unsafe { output_setting(obs::obs_frontend_get_recording_output(), name) }
+    }
+
+    fn streaming_active(&self) -> bool {
+        unsafe { obs::obs_frontend_streaming_active() }
+    }
+
+    fn streaming_start(&self) {
+        unsafe { obs::obs_frontend_streaming_start() }
+    }
+
+    fn streaming_stop(&self) {
+        unsafe { obs::obs_frontend_streaming_stop() }
+    }
+
+    fn streaming_stats(&self) -> Option<OutputStats> {
+        unsafe { output_stats(obs::obs_frontend_get_streaming_output()) }
+    }
+
+    fn streaming_reconnecting(&self) -> bool {
+        unsafe {
+            let output = obs::obs_frontend_get_streaming_output();
+            if output.is_null() {
+                return false;
+            }
+            let output = FileOutput(output);
+            obs::obs_output_reconnecting(output.0)
+        }
+    }
+
+    fn streaming_congestion(&self) -> f32 {
+        unsafe {
+            let output = obs::obs_frontend_get_streaming_output();
+            if output.is_null() {
+                return 0.0;
+            }
+            let output = FileOutput(output);
+            obs::obs_output_get_congestion(output.0)
+        }
+    }
+
+    fn replay_buffer_active(&self) -> bool {
+        unsafe { obs::obs_frontend_replay_buffer_active() }
+    }
+
+    fn replay_buffer_start(&self) {
+        unsafe { obs::obs_frontend_replay_buffer_start() }
+    }
+
+    fn replay_buffer_stop(&self) {
+        unsafe { obs::obs_frontend_replay_buffer_stop() }
+    }
+
+    fn replay_buffer_save(&self) {
+        unsafe { obs::obs_frontend_replay_buffer_save() }
+    }
+
+    fn replay_buffer_last_path(&self) -> Option<String> {
+        unsafe { take_string(obs::obs_frontend_get_last_replay()) }
+    }
+
+    fn replay_buffer_setting(&self, name: &str) -> Option<String> {
+        unsafe { output_setting(obs::obs_frontend_get_replay_buffer_output(), name) }
+    }
+
+    fn replay_buffer_set_setting(&self, name: &str, value: &str) {
+        let (name, value) = match (c_string(name), c_string(value)) {
+            (Some(name), Some(value)) => (name, value),
+            _ => return
+        };
+        unsafe {
+            let output = obs::obs_frontend_get_replay_buffer_output();
+            if output.is_null() {
+                return;
+            }
+            let output = FileOutput(output);
+            let settings = OutputData(obs::obs_data_create());
+            obs::obs_data_set_string(settings.0, name.as_ptr(), value.as_ptr());
+            obs::obs_output_update(output.0, settings.0);
+        }
+    }
+
+    fn current_scene(&self) -> Option<String> {
+        unsafe { source_name(obs::obs_frontend_get_current_scene()) }
+    }
+
+    fn scene_names(&self) -> Vec<String> {
+        unsafe { take_string_list(obs::obs_frontend_get_scene_names()) }
+    }
+
+    fn set_current_scene(&self, name: &str) {
+        unsafe {
+            if let Some(scene) = scene_by_name(name) {
+                obs::obs_frontend_set_current_scene(scene.0);
+            }
+        }
+    }
+
+    fn studio_mode_active(&self) -> bool {
+        unsafe { obs::obs_frontend_preview_program_mode_active() }
+    }
+
+    fn preview_scene(&self) -> Option<String> {
+        unsafe { source_name(obs::obs_frontend_get_current_preview_scene()) }
+    }
+
+    fn set_preview_scene(&self, name: &str) {
+        unsafe {
+            if let Some(scene) = scene_by_name(name) {
+                obs::obs_frontend_set_current_preview_scene(scene.0);
+            }
+        }
+    }
+
+    fn scene_items(&self, scene: &str) -> Option<Vec<SceneItem>> {
+        unsafe {
+            let scene = scene_by_name(scene)?;
+            let mut items: Vec<SceneItem> = Vec::new();
+            obs::obs_scene_enum_items(obs::obs_scene_from_source(scene.0), Some(collect_item),
+                                      &mut items as *mut Vec<SceneItem> as *mut c_void);
+            Some(items)
+        }
+    }
+
+    fn set_scene_item_visible(&self, scene: &str, source: &str, visible: bool) {
+        unsafe {
+            if let (Some(scene), Some(source)) = (scene_by_name(scene), c_string(source)) {
+                let item = obs::obs_scene_find_source(obs::obs_scene_from_source(scene.0), source.as_ptr());
+                if !item.is_null() {
+                    obs::obs_sceneitem_set_visible(item, visible);
+                }
+            }
+        }
+    }
+
+    fn source_exists(&self, name: &str) -> bool {
+        unsafe { source_by_name(name).is_some() }
+    }
+
+    fn source_audio(&self, name: &str) -> Option<AudioState> {
+        unsafe {
+            let source = audio_source_by_name(name)?;
+            Some(AudioState { muted: obs::obs_source_muted(source.0), volume: obs::obs_source_get_volume(source.0) })
+        }
+    }
+
+    fn set_source_muted(&self, name: &str, muted: bool) {
+        unsafe {
+            if let Some(source) = audio_source_by_name(name) {
+                obs::obs_source_set_muted(source.0, muted);
+            }
+        }
+    }
+
+    fn set_source_volume(&self, name: &str, volume: f32) {
+        unsafe {
+            if let Some(source) = audio_source_by_name(name) {
+                obs::obs_source_set_volume(source.0, volume);
+            }
+        }
+    }
+
+    fn scene_collections(&self) -> Vec<String> {
+        unsafe { take_string_list(obs::obs_frontend_get_scene_collections()) }
+    }
+
+    fn current_scene_collection(&self) -> Option<String> {
+        unsafe { take_string(obs::obs_frontend_get_current_scene_collection()) }
+    }
+
+    fn set_scene_collection(&self, name: &str) {
+        if let Some(name) = c_string(name) {
+            unsafe { obs::obs_frontend_set_current_scene_collection(name.as_ptr()) }
+        }
+    }
+}
+
+/// Maps the OBS frontend events forwarded to clients.
+pub fn event_kind(event: obs::obs_frontend_event) -> Option<EventKind> {
+    Some(match event {
+        obs::obs_frontend_event_OBS_FRONTEND_EVENT_RECORDING_STARTING => EventKind::RecordingStarting,
+        obs::obs_frontend_event_OBS_FRONTEND_EVENT_RECORDING_STARTED => EventKind::RecordingStarted,
+        obs::obs_frontend_event_OBS_FRONTEND_EVENT_RECORDING_STOPPING => EventKind::RecordingStopping,
+        obs::obs_frontend_event_OBS_FRONTEND_EVENT_RECORDING_STOPPED => EventKind::RecordingStopped,
+        obs::obs_frontend_event_OBS_FRONTEND_EVENT_RECORDING_PAUSED => EventKind::RecordingPaused,
+        obs::obs_frontend_event_OBS_FRONTEND_EVENT_RECORDING_UNPAUSED => EventKind::RecordingUnpaused,
+        obs::obs_frontend_event_OBS_FRONTEND_EVENT_STREAMING_STARTING => EventKind::StreamingStarting,
+        obs::obs_frontend_event_OBS_FRONTEND_EVENT_STREAMING_STARTED => EventKind::StreamingStarted,
+        obs::obs_frontend_event_OBS_FRONTEND_EVENT_STREAMING_STOPPING => EventKind::StreamingStopping,
+        obs::obs_frontend_event_OBS_FRONTEND_EVENT_STREAMING_STOPPED => EventKind::StreamingStopped,
+        obs::obs_frontend_event_OBS_FRONTEND_EVENT_REPLAY_BUFFER_STARTING => EventKind::ReplayBufferStarting,
+        obs::obs_frontend_event_OBS_FRONTEND_EVENT_REPLAY_BUFFER_STARTED => EventKind::ReplayBufferStarted,
+        obs::obs_frontend_event_OBS_FRONTEND_EVENT_REPLAY_BUFFER_STOPPING => EventKind::ReplayBufferStopping,
+        obs::obs_frontend_event_OBS_FRONTEND_EVENT_REPLAY_BUFFER_STOPPED => EventKind::ReplayBufferStopped,
+        obs::obs_frontend_event_OBS_FRONTEND_EVENT_REPLAY_BUFFER_SAVED => EventKind::ReplayBufferSaved,
+        obs::obs_frontend_event_OBS_FRONTEND_EVENT_SCENE_CHANGED => EventKind::SceneChanged,
+        _ => return None
+    })
+}
+
+/// Reads the statistics of an output, taking ownership of the output reference.
+unsafe fn output_stats(output: *mut obs::obs_output_t) -> Option<OutputStats> {
+    if output.is_null() {
+        return None;
+    }
+    let output = FileOutput(output);
+    Some(OutputStats {
+        bytes: obs::obs_output_get_total_bytes(output.0),
+        dropped_frames: obs::obs_output_get_frames_dropped(output.0).max(0) as u32,
+        total_frames: obs::obs_output_get_total_frames(output.0).max(0) as u32,
+    })
+}
+
+/// Reads a string value from the settings of an output, taking ownership of the output reference.
+unsafe fn output_setting(output: *mut obs::obs_output_t, name: &str) -> Option<String> {
+    if output.is_null() {
+        return None;
+    }
+    let output = FileOutput(output);
+    let name = c_string(name)?;
+    let settings = obs::obs_output_get_settings(output.0);
+    if settings.is_null() {
+        return None;
+    }
+    let settings = OutputData(settings);
+    to_string(obs::obs_data_get_string(settings.0, name.as_ptr()))
+}
+
+/// Converts a string for OBS, which can't represent NUL characters.
+/// Requests containing them are rejected by the core, so this only keeps a stray one from panicking.
+fn c_string(s: &str) -> Option<CString> {
+    CString::new(s).ok()
+}
+
+/// Gets the name of a source, taking ownership of the source reference.
+unsafe fn source_name(source: *mut obs::obs_source_t) -> Option<String> {
+    if source.is_null() {
+        return None;
+    }
+    let source = Source(source);
+    to_string(obs::obs_source_get_name(source.0))
+}
+
+pub(crate) unsafe fn source_by_name(name: &str) -> Option<Source> {
+    let name = c_string(name)?;
+    let source = obs::obs_get_source_by_name(name.as_ptr());
+    if source.is_null() {
+        None
+    } else {
+        Some(Source(source))
+    }
+}
+
+/// Finds a scene of the current scene collection.
+unsafe fn scene_by_name(name: &str) -> Option<Source> {
+    source_by_name(name).filter(|source| !obs::obs_scene_from_source(source.0).is_null())
+}
+
+unsafe fn audio_source_by_name(name: &str) -> Option<Source> {
+    source_by_name(name).filter(|source| obs::obs_source_get_output_flags(source.0) & obs::OBS_SOURCE_AUDIO != 0)
+}
+
+/// `obs_scene_enum_items` callback, adding the items to a `Vec<SceneItem>`.
+unsafe extern "C" fn collect_item(_scene: *mut obs::obs_scene_t, item: *mut obs::obs_sceneitem_t, param: *mut c_void) -> bool {
+    let items = &mut *(param as *mut Vec<SceneItem>);
+    if let Some(name) = to_string(obs::obs_source_get_name(obs::obs_sceneitem_get_source(item))) {
+        items.push(SceneItem { name, visible: obs::obs_sceneitem_visible(item) });
+    }
+    true
+}
+
+/// Copies a C string allocated by OBS for the caller, then frees it.
+unsafe fn take_string(ptr: *mut c_char) -> Option<String> {
+    let res = to_string(ptr);
+    obs::bfree(ptr as *mut c_void);
+    res
+}
+
+/// Copies a null-terminated array of C strings allocated by OBS for the caller, then frees it.
+unsafe fn take_string_list(list: *mut *mut c_char) -> Vec<String> {
+    let mut res = Vec::new();
+    if list.is_null() {
+        return res;
+    }
+    let mut item = list;
+    while !(*item).is_null() {
+        res.extend(to_string(*item));
+        item = item.add(1);
+    }
+    obs::bfree(list as *mut c_void);
+    res
+}
+
+/// Copies a C string owned by OBS, returning `None` for null pointers.
+unsafe fn to_string(ptr: *const c_char) -> Option<String> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+    }
+}
+
+struct FileOutput(*mut obs::obs_output_t);
+struct OutputData(*mut obs::obs_data_t);
+/// Releases the source reference when dropped.
+pub(crate) struct Source(pub(crate) *mut obs::obs_source_t);
+
+impl Drop for FileOutput {
+    fn drop(&mut self) {
+        unsafe { obs::obs_output_release(self.0); }
+    }
+}
+
+impl Drop for OutputData {
+    fn drop(&mut self) {
+        unsafe { obs::obs_data_release(self.0); }
+    }
+}
+
+impl Drop for Source {
+    fn drop(&mut self) {
+        unsafe { obs::obs_source_release(self.0); }
+    }
+}
@@ -16,173 +16,108 @@
  *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use crate::dialog::{AppInfo, Dialog, DialogResult};
-use crate::recording::RecordingState;
-use crate::server::HttpServer;
-use crate::verification::VerificationResult;
+use crate::dialog::DialogApprover;
+use crate::frontend::ObsFrontend;
+use crate::screenshot::ObsFrames;
 use obs::obs_module_t;
-use std::borrow::Cow;
-use std::convert::TryInto;
-use std::ffi::CStr;
-use std::io::{ErrorKind, Read};
+use obs_controller_core::server::{HttpServer, ShutdownHandle};
+use obs_controller_core::{routes, AppStore, Config, Controller};
 use std::os::raw::{c_char, c_void};
 use std::ptr;
-use std::sync::{Arc, Mutex};
-use std::thread;
-use uuid::Uuid;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
 #[macro_use]
 mod macros;
 
 mod dialog;
+mod frontend;
+mod logger;
 mod obs;
-mod recording;
-mod server;
-mod verification;
+mod screenshot;
 
 static mut MODULE: Option<*mut obs_module_t> = None;
 const MODULE_NAME: &str = concat!(env!("CARGO_PKG_NAME"), "\0");
 const MODULE_DESC: &str = concat!(env!("CARGO_PKG_DESCRIPTION"), "\0");
+const APPS_MENU_ITEM: &str = "OBS Controller Apps\0";
+/// How long unloading waits for in-flight requests.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
 
 lazy_static::lazy_static! {
-    static ref STATE: Arc<Mutex<Option<RecordingState>>> = Arc::new(Mutex::new(None));
-    static ref APPS_FILE: String = format!("{}/obs-controller/apps.ock", dirs::data_dir().map(|p| p.to_str().unwrap_or(".").to_string()).unwrap_or_else(|| ".".to_string()));
-}
-
-#[derive(serde::Deserialize)]
-struct AppRegistrationData {
-    uuid: String,
-    name: String,
-    public_key: String,
-}
-
-macro_rules! validate_input {
-    ($condition: expr, $desc: literal, $code: literal) => {
-        if !($condition) {
-            return (
-                $code,
-                Cow::Borrowed(concat!(r#"{"message": ""#, $desc, r#""}"#)),
-            );
-        }
-    };
+    static ref DATA_DIR: String = format!("{}/obs-controller", dirs::data_dir().map(|p| p.to_str().unwrap_or(".").to_string()).unwrap_or_else(|| ".".to_string()));
+    static ref APPS_FILE: String = format!("{}/apps.ock", *DATA_DIR);
+    static ref CONFIG_FILE: String = format!("{}/config.json", *DATA_DIR);
+    static ref CONTROLLER: Arc<Controller> = Arc::new(Controller::new(
+        Arc::new(ObsFrontend),
+        AppStore::new(&*APPS_FILE),
+        Box::new(DialogApprover),
+    ).with_frames(Arc::new(ObsFrames)));
+    static ref SERVER: Mutex<Option<(ShutdownHandle, JoinHandle<()>)>> = Mutex::new(None);
 }
 
 #[no_mangle]
 pub extern "C" fn obs_module_load() -> bool {
     println!("[OBS Controller] Load started.");
+    logger::init();
     // Signals
     unsafe {
-        obs::obs_frontend_add_event_callback(Some(on_recording_stopped), ptr::null_mut());
+        obs::obs_frontend_add_event_callback(Some(on_frontend_event), ptr::null_mut());
+        obs::obs_frontend_add_tools_menu_item(APPS_MENU_ITEM.as_ptr() as *const c_char, Some(on_manage_apps), ptr::null_mut());
     }
-    let obs_version = unsafe {
-        CStr::from_ptr(obs::obs_get_version_string())
-            .to_str()
-            .expect("Invalid version string")
-    };
 
     // Web server
-    thread::spawn(move || {
-        let mut server = HttpServer::new(8085);
-        let info = format!(
-            r#"{{"version": "{}", "obs": "{}"}}"#,
-            env!("CARGO_PKG_VERSION"),
-            obs_version
-        );
-        server.add_route(
-            "/",
-            Box::new(move |req| req.respond(server::json_response(200, &info))),
-        );
-        server.add_route("/register", Box::new(move |mut req| {
-            let (tx, rx) = std::sync::mpsc::channel();
-            let (status, res): (u16, Cow<str>) = (|| {
-                let mut body = String::with_capacity(1024.min(req.body_length().unwrap_or(1024)));
-                let body_res = req.as_reader().take(1024).read_to_string(&mut body);
-                validate_input!(body_res.is_ok() && !body.is_empty(), "Body cannot be empty", 400);
-                let data = serde_json::from_str::<AppRegistrationData>(&body);
-                validate_input!(data.is_ok(), "Invalid json data, check the docs for a valid schema", 400);
-                let data = data.unwrap();
-                validate_input!(data.uuid.len() == 36 || data.uuid.len() == 32, "Invalid UUID. Only stripped and hyphenated UUIDs are supported.", 400);
-                validate_input!(!data.name.is_empty() && data.name.len() <= 24, "Name length must be within (0;24]", 400);
-                let uuid = Uuid::parse_str(&data.uuid);
-                validate_input!(uuid.is_ok(), "Invalid UUID", 400);
-                let bytes = base64::decode(&data.public_key);
-                validate_input!(bytes.is_ok(), "Invalid Base64", 400);
-                let bytes = bytes.unwrap();
-                validate_input!(bytes.len() == 32, "Public key must be 32 bytes in length", 400);
-                let bytes: [u8; 32] = bytes.try_into().unwrap();
-                let pub_key = x25519_dalek::PublicKey::from(bytes);
-                Dialog::new(AppInfo::new(data.name), Box::new(tx)).open();
-                match rx.recv().unwrap() {
-                    DialogResult::Accepted(app) => {
-                        let registration = verification::register_encrypt(uuid.unwrap(), app.name.to_str().unwrap().to_string(), pub_key);
-                        match registration {
-                            Ok((secret, our_pk)) => (200, Cow::Owned(format!(r#"{{"key": "{}", "shared_public": "{}"}}"#, secret, our_pk))),
-                            Err(e) if e.kind() == ErrorKind::AlreadyExists => (409, Cow::Borrowed(r#"{"message": "An app with the same UUID already exists"}"#)),
-                            Err(e) => {
-                                eprintln!("User request refused due to an error {:?}", e);
-                                (500, Cow::Borrowed(r#"{"message": "The request couldn't be fulfilled due to an error"}"#))
-                            }
-                        }
-                    }
-                    DialogResult::Denied => (401, Cow::Borrowed(r#"{"message": "The registration request was denied by the user"}"#))
-                }
-            })();
-            req.respond(server::json_response(status, &res))
-        }));
-        server.add_route(
-            "/recording/start",
-            Box::new(|mut req| {
-                let body = verification::middleware_auth(&mut req).unwrap();
-                let (status, msg): (u16, &str) = match body {
-                    VerificationResult::Body(body) => {
-                        let recording = if body.is_empty() {
-                            RecordingState::start()
-                        } else {
-                            RecordingState::start_with_name(body).expect("Couldn't start recording")
-                        };
-                        *STATE.lock().expect("Poisoned Mutex") = Some(recording);
-                        (200, r#"{"message": "Recording started"}"#)
-                    }
-                    VerificationResult::JsonReject(status, msg) => (status, msg),
-                };
-                req.respond(server::json_response(status, &msg))
-            }),
-        );
-        server.add_route(
-            "/recording/stop",
-            Box::new(|mut req| {
-                let body = verification::middleware_auth(&mut req).unwrap();
-                let (status, msg): (u16, Cow<str>) = match body {
-                    VerificationResult::Body(_) => {
-                        let res = RecordingState::stop();
-                        (200, Cow::Owned(serde_json::to_string(&res).unwrap()))
-                    }
-                    VerificationResult::JsonReject(status, msg) => (status, Cow::Borrowed(msg)),
-                };
-                req.respond(server::json_response(status, &msg))
-            }),
-        );
-        server.run().expect("Couldn't run HTTP server.");
+    let config = Config::load_or_create(&*CONFIG_FILE).unwrap_or_else(|e| {
+        eprintln!("[OBS Controller] Couldn't read {}, using the default settings: {}", *CONFIG_FILE, e);
+        Config::default()
+    });
+    CONTROLLER.recorder.set_ffmpeg_chapters(config.ffmpeg_chapters);
+    CONTROLLER.apps.set_legacy_signatures(config.legacy_signatures);
+    let mut server = HttpServer::new(config);
+    routes::add_routes(&mut server, Arc::clone(&CONTROLLER));
+    let shutdown = server.shutdown_handle();
+    let thread = thread::spawn(move || {
+        if let Err(e) = server.run() {
+            eprintln!("[OBS Controller] Couldn't run HTTP server: {}", e);
+        }
     });
+    *SERVER.lock().unwrap_or_else(PoisonError::into_inner) = Some((shutdown, thread));
     println!("[OBS Controller] Load finished.");
     true
 }
 
 #[no_mangle]
 pub extern "C" fn obs_module_unload() -> bool {
+    // Ends the event streams, which would otherwise keep their connections open
+    CONTROLLER.events.close();
+    if let Some((shutdown, thread)) = SERVER.lock().unwrap_or_else(PoisonError::into_inner).take() {
+        if shutdown.shutdown(SHUTDOWN_TIMEOUT) {
+            thread.join().ok();
+        } else {
+            eprintln!("[OBS Controller] The HTTP server didn't stop in time.");
+        }
+    }
+    unsafe {
+        obs::obs_frontend_remove_event_callback(Some(on_frontend_event), ptr::null_mut());
+    }
     println!("[OBS Controller] Unloaded.");
     true
 }
 
-extern "C" fn on_recording_stopped(event: obs::obs_frontend_event, _private_data: *mut c_void) {
-    if event == obs::obs_frontend_event_OBS_FRONTEND_EVENT_RECORDING_STOPPED {
-        let mut lock = STATE.lock().expect("Poisoned Mutex");
-        if let Some(state) = lock.take() {
-            unsafe {
-                state.revert_name();
-            }
-        }
+extern "C" fn on_frontend_event(event: obs::obs_frontend_event, _private_data: *mut c_void) {
+    if event == obs::obs_frontend_event_OBS_FRONTEND_EVENT_EXIT
+        || event == obs::obs_frontend_event_OBS_FRONTEND_EVENT_PROFILE_CHANGING {
+        // OBS saves the profile config when exiting or switching profiles,
+        // which must not keep the custom file name or directory of a recording that is starting
+        CONTROLLER.recorder.revert_overrides();
     }
+    if let Some(kind) = frontend::event_kind(event) {
+        CONTROLLER.handle_event(kind);
+    }
+}
+
+extern "C" fn on_manage_apps(_private_data: *mut c_void) {
+    dialog::manage_apps(&CONTROLLER.apps);
 }
 
 // OBS Module load helpers - these would be declared by the OBS_DECLARE_MODULE() C macro
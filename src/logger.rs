@@ -0,0 +1,52 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use log::{Level, LevelFilter, Metadata, Record};
+use std::ffi::CString;
+use std::os::raw::c_char;
+
+use crate::obs;
+
+/// Forwards the log records of the core crate to the OBS log.
+struct ObsLogger;
+
+impl log::Log for ObsLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let level = match record.level() {
+            Level::Error => obs::LOG_ERROR,
+            Level::Warn => obs::LOG_WARNING,
+            Level::Info => obs::LOG_INFO,
+            Level::Debug | Level::Trace => obs::LOG_DEBUG,
+        };
+        let message = format!("[OBS Controller] {}", record.args()).replace('\0', "");
+        let c_str = CString::new(message).unwrap_or_default();
+        unsafe { obs::blog(level as i32, "%s\0".as_ptr() as *const c_char, c_str.as_ptr()); }
+    }
+
+    fn flush(&self) {}
+}
+
+pub fn init() {
+    if log::set_logger(&ObsLogger).is_ok() {
+        log::set_max_level(LevelFilter::Debug);
+    }
+}
@@ -1,16 +1,17 @@
 macro_rules! println {
     () => (println!(""));
     ($($arg:tt)*) => ({
-        let c_str = std::ffi::CString::new(format!($($arg)*)).expect("Couldn't create C-string");
-        unsafe { $crate::obs::blog($crate::obs::LOG_INFO as i32, c_str.as_ptr()); }
+        let c_str = std::ffi::CString::new(format!($($arg)*).replace('\0', "")).unwrap_or_default();
+        unsafe { $crate::obs::blog($crate::obs::LOG_INFO as i32, "%s\0".as_ptr() as *const std::os::raw::c_char, c_str.as_ptr()); }
     })
 }
 
+#[allow(unused_macros)]
 macro_rules! eprintln {
     () => (eprintln!(""));
     ($($arg:tt)*) => ({
-        let c_str = std::ffi::CString::new(format!($($arg)*)).expect("Couldn't create C-string");
-        unsafe { $crate::obs::blog($crate::obs::LOG_ERROR as i32, c_str.as_ptr()); }
+        let c_str = std::ffi::CString::new(format!($($arg)*).replace('\0', "")).unwrap_or_default();
+        unsafe { $crate::obs::blog($crate::obs::LOG_ERROR as i32, "%s\0".as_ptr() as *const std::os::raw::c_char, c_str.as_ptr()); }
     })
 }
 
@@ -18,7 +19,7 @@ macro_rules! eprintln {
 macro_rules! debug {
     () => (debug!(""));
     ($($arg:tt)*) => ({
-        let c_str = std::ffi::CString::new(format!($($arg)*)).expect("Couldn't create C-string");
-        unsafe { $crate::obs::blog($crate::obs::LOG_DEBUG as i32, c_str.as_ptr()); }
+        let c_str = std::ffi::CString::new(format!($($arg)*).replace('\0', "")).unwrap_or_default();
+        unsafe { $crate::obs::blog($crate::obs::LOG_DEBUG as i32, "%s\0".as_ptr() as *const std::os::raw::c_char, c_str.as_ptr()); }
     })
 }
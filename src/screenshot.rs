@@ -0,0 +1,88 @@
+/*
+ *  This file is part of OBS Controller.
+ *  Copyright (C) 2020 Beezig Team
+ *
+ *  This program is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU General Public License as published by
+ *  the Free Software Foundation, either version 3 of the License, or
+ *  (at your option) any later version.
+ *
+ *  This program is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ *  GNU General Public License for more details.
+ *
+ *  You should have received a copy of the GNU General Public License
+ *  along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::ptr;
+
+use obs_controller_core::screenshot::{CaptureError, Frame, FrameProvider};
+
+use crate::frontend::{source_by_name, Source};
+use crate::obs;
+
+/// [`FrameProvider`] rendering sources with the OBS graphics subsystem.
+pub struct ObsFrames;
+
+impl FrameProvider for ObsFrames {
+    fn capture(&self, source: Option<&str>, size: Option<(u32, u32)>) -> Result<Frame, CaptureError> {
+        unsafe {
+            let source = match source {
+                Some(name) => source_by_name(name).ok_or(CaptureError::UnknownSource)?,
+                // The program output
+                None => {
+                    let scene = obs::obs_frontend_get_current_scene();
+                    if scene.is_null() {
+                        return Err(CaptureError::Failed);
+                    }
+                    Source(scene)
+                }
+            };
+            let (source_width, source_height) = (obs::obs_source_get_width(source.0), obs::obs_source_get_height(source.0));
+            if source_width == 0 || source_height == 0 {
+                return Err(CaptureError::Failed);
+            }
+            let (width, height) = size.unwrap_or((source_width, source_height));
+            obs::obs_enter_graphics();
+            let res = render(&source, (source_width, source_height), width, height);
+            obs::obs_leave_graphics();
+            res
+        }
+    }
+}
+
+/// Renders a source scaled to the given size, then copies the pixels back from the GPU.
+/// Must be called in the graphics context.
+unsafe fn render(source: &Source, (source_width, source_height): (u32, u32), width: u32, height: u32) -> Result<Frame, CaptureError> {
+    let texrender = obs::gs_texrender_create(obs::gs_color_format_GS_RGBA, obs::gs_zstencil_format_GS_ZS_NONE);
+    let stagesurface = obs::gs_stagesurface_create(width, height, obs::gs_color_format_GS_RGBA);
+    let mut res = Err(CaptureError::Failed);
+    if obs::gs_texrender_begin(texrender, width, height) {
+        let clear: obs::vec4 = std::mem::zeroed();
+        obs::gs_clear(obs::GS_CLEAR_COLOR, &clear, 0.0, 0);
+        obs::gs_ortho(0.0, source_width as f32, 0.0, source_height as f32, -100.0, 100.0);
+        obs::gs_blend_state_push();
+        obs::gs_blend_function(obs::gs_blend_type_GS_BLEND_ONE, obs::gs_blend_type_GS_BLEND_ZERO);
+        obs::obs_source_video_render(source.0);
+        obs::gs_blend_state_pop();
+        obs::gs_texrender_end(texrender);
+
+        obs::gs_stage_texture(stagesurface, obs::gs_texrender_get_texture(texrender));
+        let mut data: *mut u8 = ptr::null_mut();
+        let mut linesize = 0u32;
+        if obs::gs_stagesurface_map(stagesurface, &mut data, &mut linesize) {
+            let row = width as usize * 4;
+            let mut pixels = Vec::with_capacity(row * height as usize);
+            for y in 0..height as usize {
+                pixels.extend_from_slice(std::slice::from_raw_parts(data.add(y * linesize as usize), row));
+            }
+            obs::gs_stagesurface_unmap(stagesurface);
+            res = Ok(Frame { width, height, data: pixels });
+        }
+    }
+    obs::gs_stagesurface_destroy(stagesurface);
+    obs::gs_texrender_destroy(texrender);
+    res
+}